@@ -54,10 +54,15 @@ fn perlinNoise2(P: vec2<f32>) -> f32 {
 }
 
 struct AsphaltMaterial {
-    quality: i32// 0-10
+    quality: i32, // 0-10
+    wetness: f32, // 0-1
 };
 @group(#{MATERIAL_BIND_GROUP}) @binding(100)
 var<uniform> material: AsphaltMaterial;
+@group(#{MATERIAL_BIND_GROUP}) @binding(101)
+var rubber_texture: texture_2d<f32>;
+@group(#{MATERIAL_BIND_GROUP}) @binding(102)
+var rubber_sampler: sampler;
 
 const coeff_l: f32 = 0.035;
 const coeff_m: f32 = 0.35;
@@ -135,6 +140,16 @@ fn fragment(
         pbr_input.N = in.world_normal;
     }
 
+#ifdef VERTEX_UVS_B
+    // rubber laid by the tyres darkens the racing line, uv_b runs across and along the road
+    var rubber: f32 = textureSample(rubber_texture, rubber_sampler, in.uv_b).r;
+    pbr_input.material.base_color = pbr_input.material.base_color * vec4<f32>(vec3<f32>(1. - 0.5 * rubber), 1.);
+#endif
+
+    // water darkens the asphalt and makes it glossy
+    pbr_input.material.base_color = pbr_input.material.base_color * vec4<f32>(vec3<f32>(1. - 0.4 * material.wetness), 1.);
+    pbr_input.material.perceptual_roughness = mix(pbr_input.material.perceptual_roughness, 0.1, material.wetness);
+
     var out: FragmentOutput;
     out.color = apply_pbr_lighting(pbr_input);
     out.color = out.color;
@@ -4,12 +4,33 @@ use std::f32::consts::PI;
 
 use crate::{Car, CarSpec, CarWheels, Wheel};
 
-pub fn aero_system(mut car_query: Query<(&Velocity, &Transform, &mut ExternalForce), With<Car>>) {
+/// Air the cars drive through.
+#[derive(Resource, Debug)]
+pub struct Air {
+    /// Density in kg/m3.
+    pub density: f32,
+    /// Extra drag multiplier, rain drops hitting the car.
+    pub drag: f32,
+}
+
+impl Default for Air {
+    fn default() -> Self {
+        Self {
+            density: 1.2,
+            drag: 1.,
+        }
+    }
+}
+
+pub fn aero_system(
+    air: Res<Air>,
+    mut car_query: Query<(&Velocity, &Transform, &mut ExternalForce), With<Car>>,
+) {
     for (velocity, transform, mut force) in car_query.iter_mut() {
         let car_vector = transform.rotation.mul_vec3(Vec3::Z);
         let car_vector_norm = car_vector.normalize();
         let car_mps = velocity.linvel.length();
-        let f_drag = 1. / 2. * 1.2 * car_mps.powi(2) * 0.2 * 1.5;
+        let f_drag = 1. / 2. * air.density * car_mps.powi(2) * 0.2 * 1.5 * air.drag;
         let f_down = car_mps.powi(2) * 2.;
         // println!("drag:{f_drag:.1} down:{f_down:.1}");
         force.force = -Vec3::Y * f_down - car_vector_norm * f_drag;
@@ -1,11 +1,105 @@
+use bevy::camera::Exposure;
 use bevy::light::{GlobalAmbientLight, NotShadowCaster};
 use bevy::prelude::*;
+use bevy_garage_car::{Car, CarSpec};
+use bevy_garage_track::NightLight;
+use std::f32::consts::PI;
+
+const SUN_ILLUMINANCE: f32 = 10_000.;
+const MOON_ILLUMINANCE: f32 = 30.;
+/// Sun elevation at noon.
+const SUN_MAX_ELEVATION: f32 = PI / 3.;
+/// Smallest sky color change written to its material, one step of an 8 bit channel.
+const SKY_COLOR_STEP: f32 = 1. / 255.;
+/// Cars nearest to the camera with their headlights on, every spot light costs a pass.
+const HEADLIGHT_CARS: usize = 4;
+
+#[derive(Resource, Debug)]
+pub struct TimeOfDay {
+    /// Hour of the day from 0 to 24.
+    pub hour: f32,
+    /// Game hours passing per real second, 0 stops the clock.
+    pub speed: f32,
+}
+
+impl Default for TimeOfDay {
+    fn default() -> Self {
+        Self {
+            hour: 10.,
+            speed: 1. / 60.,
+        }
+    }
+}
+
+impl TimeOfDay {
+    /// Sun elevation above the horizon in radians, negative at night.
+    pub fn sun_elevation(&self) -> f32 {
+        SUN_MAX_ELEVATION * (PI * (self.hour - 6.) / 12.).sin()
+    }
+    /// Sun azimuth in radians, zero at noon.
+    pub fn sun_azimuth(&self) -> f32 {
+        PI * (self.hour - 12.) / 12.
+    }
+    /// Unit vector pointing towards the sun.
+    pub fn sun_direction(&self) -> Vec3 {
+        let (elevation, azimuth) = (self.sun_elevation(), self.sun_azimuth());
+        Vec3::new(
+            azimuth.sin() * elevation.cos(),
+            elevation.sin(),
+            -azimuth.cos() * elevation.cos(),
+        )
+    }
+    /// Amount of daylight from 0 at night to 1 during the day, fading through twilight.
+    pub fn daylight(&self) -> f32 {
+        let x = ((self.sun_elevation().to_degrees() + 6.) / 16.).clamp(0., 1.);
+        x * x * (3. - 2. * x)
+    }
+    pub fn is_dark(&self) -> bool {
+        self.daylight() < 0.5
+    }
+    /// Sky color, blue at day, red around sunrise and sunset, dark blue at night.
+    pub fn sky_color(&self) -> Color {
+        let day: Color = Srgba::hex("87CEEB").unwrap().into();
+        let dusk = Color::srgb(0.9, 0.5, 0.3);
+        let night = Color::srgb(0.01, 0.015, 0.04);
+        let twilight = 1. - (self.sun_elevation().to_degrees().abs() / 10.).clamp(0., 1.);
+        night.mix(&day.mix(&dusk, twilight), self.daylight())
+    }
+}
+
+#[derive(Component, Debug)]
+pub struct Sky;
+
+#[derive(Component, Debug)]
+pub struct CarLights;
+
+#[derive(Component, Debug)]
+pub struct BrakeLight;
+
+#[derive(Component, Debug)]
+pub struct Headlight;
+
+/// Brake light materials shared by all cars, released and braking.
+#[derive(Resource, Debug)]
+pub struct BrakeLightMaterials {
+    pub off: Handle<StandardMaterial>,
+    pub on: Handle<StandardMaterial>,
+}
 
 pub fn light_start_system(
     mut cmd: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
 ) {
+    let brake_light = |emissive: LinearRgba| StandardMaterial {
+        base_color: Color::srgb(0.3, 0., 0.),
+        emissive,
+        ..default()
+    };
+    cmd.insert_resource(BrakeLightMaterials {
+        off: materials.add(brake_light(LinearRgba::BLACK)),
+        on: materials.add(brake_light(LinearRgba::rgb(20., 0., 0.))),
+    });
     cmd.insert_resource(GlobalAmbientLight {
         color: Color::srgb_u8(210, 220, 240),
         brightness: 80.,
@@ -14,7 +108,7 @@ pub fn light_start_system(
 
     cmd.spawn((
         DirectionalLight {
-            illuminance: 10_000.,
+            illuminance: SUN_ILLUMINANCE,
             shadows_enabled: true,
             ..default()
         },
@@ -35,34 +129,202 @@ pub fn light_start_system(
         })),
         Transform::from_scale(Vec3::splat(10000.0)),
         NotShadowCaster,
+        Sky,
     ));
 }
 
 const K: f32 = 2.;
 
-pub fn animate_light_direction(
+/// Runs the clock, H and L move the time of day back and forth.
+pub fn time_of_day_system(
     time: Res<Time>,
-    mut query: Query<&mut Transform, With<DirectionalLight>>,
+    mut time_of_day: ResMut<TimeOfDay>,
     input: Res<ButtonInput<KeyCode>>,
 ) {
+    let mut hours = time.delta_secs() * time_of_day.speed;
     if input.pressed(KeyCode::KeyH) {
-        for mut transform in &mut query {
-            transform.rotate_y(time.delta_secs() * K);
-        }
+        hours -= time.delta_secs() * K;
     }
     if input.pressed(KeyCode::KeyL) {
-        for mut transform in &mut query {
-            transform.rotate_y(-time.delta_secs() * K);
+        hours += time.delta_secs() * K;
+    }
+    time_of_day.hour = (time_of_day.hour + hours).rem_euclid(24.);
+}
+
+pub fn day_night_system(
+    time_of_day: Res<TimeOfDay>,
+    mut ambient: ResMut<GlobalAmbientLight>,
+    mut suns: Query<(&mut DirectionalLight, &mut Transform)>,
+    skies: Query<&MeshMaterial3d<StandardMaterial>, With<Sky>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut fogs: Query<&mut DistanceFog>,
+    mut exposures: Query<&mut Exposure>,
+) {
+    if !time_of_day.is_changed() {
+        return;
+    }
+    let daylight = time_of_day.daylight();
+    let sun = time_of_day.sun_direction();
+    // below the horizon the moon lights the scene from the opposite side
+    let light_from = if sun.y > 0. { sun } else { -sun };
+    let low_sun = (time_of_day.sun_elevation().to_degrees() / 20.).clamp(0., 1.);
+    for (mut light, mut transform) in suns.iter_mut() {
+        light.illuminance = MOON_ILLUMINANCE + (SUN_ILLUMINANCE - MOON_ILLUMINANCE) * daylight;
+        light.color = Color::srgb(0.6, 0.7, 1.).mix(
+            &Color::srgb(1., 0.6, 0.35).mix(&Color::WHITE, low_sun),
+            daylight,
+        );
+        *transform = Transform::default().looking_to(-light_from, Vec3::Y);
+    }
+    ambient.color = Color::srgb(0.2, 0.25, 0.45).mix(&Color::srgb_u8(210, 220, 240), daylight);
+    ambient.brightness = 5. + 75. * daylight;
+
+    let sky_color = time_of_day.sky_color();
+    for sky in skies.iter() {
+        // writing the material prepares it again, skip changes too small to see
+        let changed = materials.get(&sky.0).is_some_and(|material| {
+            let (old, new) = (material.base_color.to_srgba(), sky_color.to_srgba());
+            (old.to_vec4() - new.to_vec4()).abs().max_element() >= SKY_COLOR_STEP
+        });
+        if !changed {
+            continue;
+        }
+        if let Some(material) = materials.get_mut(&sky.0) {
+            material.base_color = sky_color;
         }
     }
-    if input.pressed(KeyCode::KeyJ) {
-        for mut transform in &mut query {
-            transform.rotate_x(time.delta_secs() * K);
+    for mut fog in fogs.iter_mut() {
+        fog.color = sky_color;
+    }
+    for mut exposure in exposures.iter_mut() {
+        exposure.ev100 =
+            Exposure::EV100_INDOOR + (Exposure::EV100_BLENDER - Exposure::EV100_INDOOR) * daylight;
+    }
+}
+
+pub fn night_lights_system(
+    time_of_day: Res<TimeOfDay>,
+    mut night_lights: Query<(&NightLight, Option<&mut SpotLight>, Option<&mut PointLight>)>,
+) {
+    for (night_light, spot, point) in night_lights.iter_mut() {
+        let intensity = match time_of_day.is_dark() {
+            true => night_light.intensity,
+            false => 0.,
+        };
+        if let Some(mut spot) = spot.filter(|spot| spot.intensity != intensity) {
+            spot.intensity = intensity;
+        }
+        if let Some(mut point) = point.filter(|point| point.intensity != intensity) {
+            point.intensity = intensity;
         }
     }
-    if input.pressed(KeyCode::KeyK) {
-        for mut transform in &mut query {
-            transform.rotate_x(-time.delta_secs() * K);
+}
+
+/// Fits headlights and brake lights to cars without them.
+pub fn car_lights_system(
+    mut cmd: Commands,
+    cars: Query<(Entity, &CarSpec), Without<CarLights>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    brake_materials: Res<BrakeLightMaterials>,
+) {
+    for (e, spec) in cars.iter() {
+        let (x, y, z) = (spec.size.hw - 0.3, 0., spec.size.hl);
+        let mesh = meshes.add(Cuboid::new(0.3, 0.08, 0.02));
+        cmd.entity(e).insert(CarLights).with_children(|parent| {
+            for x in [-x, x] {
+                parent.spawn((
+                    SpotLight {
+                        color: Color::srgb(1., 0.97, 0.9),
+                        intensity: 0.,
+                        range: 80.,
+                        outer_angle: 0.5,
+                        inner_angle: 0.3,
+                        shadows_enabled: false,
+                        ..default()
+                    },
+                    Transform::from_xyz(x, y, z).looking_to(Vec3::new(0., -0.05, 1.), Vec3::Y),
+                    NightLight {
+                        intensity: 2_000_000.,
+                    },
+                    // shown by headlights_system for the cars close to the camera
+                    Visibility::Hidden,
+                    Headlight,
+                ));
+                parent.spawn((
+                    Mesh3d(mesh.clone()),
+                    MeshMaterial3d(brake_materials.off.clone()),
+                    Transform::from_xyz(x, y + 0.1, -z - 0.01),
+                    NotShadowCaster,
+                    BrakeLight,
+                ));
+            }
+        });
+    }
+}
+
+/// Brake lights glow with the brake pedal, tail lights stay dimly lit at night.
+pub fn brake_lights_system(
+    time_of_day: Res<TimeOfDay>,
+    brake_materials: Res<BrakeLightMaterials>,
+    cars: Query<(&Car, &Children)>,
+    mut brake_lights: Query<&mut MeshMaterial3d<StandardMaterial>, With<BrakeLight>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let tail = match time_of_day.is_dark() {
+        true => 2.,
+        false => 0.,
+    };
+    for (handle, brake) in [(&brake_materials.off, 0.), (&brake_materials.on, 20.)] {
+        let emissive = LinearRgba::rgb(tail + brake, 0., 0.);
+        if materials
+            .get(handle)
+            .is_some_and(|material| material.emissive != emissive)
+        {
+            if let Some(material) = materials.get_mut(handle) {
+                material.emissive = emissive;
+            }
+        }
+    }
+    for (car, children) in cars.iter() {
+        let handle = match car.brake > 0. {
+            true => &brake_materials.on,
+            false => &brake_materials.off,
+        };
+        for child in children.iter() {
+            if let Ok(mut material) = brake_lights.get_mut(child) {
+                if material.0 != *handle {
+                    material.0 = handle.clone();
+                }
+            }
+        }
+    }
+}
+
+/// Turns on the headlights of the cars nearest to the camera only.
+pub fn headlights_system(
+    cameras: Query<&GlobalTransform, With<Camera3d>>,
+    cars: Query<(&GlobalTransform, &Children), With<CarLights>>,
+    mut headlights: Query<&mut Visibility, With<Headlight>>,
+) {
+    let Some(camera) = cameras.iter().next().map(|camera| camera.translation()) else {
+        return;
+    };
+    let mut cars: Vec<(f32, &Children)> = cars
+        .iter()
+        .map(|(transform, children)| (transform.translation().distance(camera), children))
+        .collect();
+    cars.sort_by(|a, b| a.0.total_cmp(&b.0));
+    for (i, (_, children)) in cars.iter().enumerate() {
+        let visibility = match i < HEADLIGHT_CARS {
+            true => Visibility::Inherited,
+            false => Visibility::Hidden,
+        };
+        for child in children.iter() {
+            if let Ok(mut headlight) = headlights.get_mut(child) {
+                if *headlight != visibility {
+                    *headlight = visibility;
+                }
+            }
         }
     }
 }
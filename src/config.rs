@@ -2,10 +2,11 @@ use bevy::prelude::*;
 
 #[derive(Resource)]
 pub struct Config {
-    // pub cars_count: usize,
+    /// Cars spawned on the grid at start, the player drives from pole.
+    pub cars_count: usize,
 }
 impl Default for Config {
     fn default() -> Self {
-        Self {}
+        Self { cars_count: 1 }
     }
 }
@@ -4,7 +4,7 @@ use bevy::{
     prelude::*,
 };
 use bevy_garage_car::Player;
-use bevy_garage_track::CarTrack;
+use bevy_garage_track::{CarTrack, LapTimer, Session, SessionState, TrackConfig};
 use bevy_rapier3d::prelude::*;
 
 #[derive(Component, Reflect)]
@@ -31,6 +31,21 @@ pub struct TrackPositionText;
 #[reflect(Component)]
 pub struct RideDistanceText;
 
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct SessionText;
+
+/// Row of start lights shown during the countdown.
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct StartLights;
+
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct StartLight(pub u32);
+
+const START_LIGHT_SIZE: f32 = 32.;
+
 pub fn dash_fps_system(
     diagnostics: Res<DiagnosticsStore>,
     mut query: Query<&mut Text, With<FpsText>>,
@@ -49,6 +64,38 @@ pub fn dash_start_system(mut cmd: Commands, asset_server: Res<AssetServer>) {
     let height = Val::Px(90.);
     let width = Val::Px(150.);
 
+    cmd.spawn(Node {
+        position_type: PositionType::Absolute,
+        top: Val::Px(96.),
+        width: Val::Percent(100.),
+        flex_direction: FlexDirection::Column,
+        align_items: AlignItems::Center,
+        row_gap: Val::Px(8.),
+        ..default()
+    })
+    .with_children(|parent| {
+        parent.spawn((
+            Text::new(""),
+            TextFont {
+                font: medium.clone(),
+                font_size: 18.0,
+                ..default()
+            },
+            TextColor(css::WHITE.into()),
+            SessionText,
+        ));
+        parent.spawn((
+            Node {
+                column_gap: Val::Px(8.),
+                padding: UiRect::all(Val::Px(8.)),
+                ..default()
+            },
+            BackgroundColor(Color::srgba(0.05, 0.05, 0.05, 0.8)),
+            Visibility::Hidden,
+            StartLights,
+        ));
+    });
+
     cmd.spawn(Node {
         width: Val::Percent(100.),
         height: height.clone(),
@@ -258,9 +305,10 @@ pub fn dash_speed_update_system(
         Query<&mut Text, With<RideDistanceText>>,
         Query<&mut Text, With<LapText>>,
     )>,
-    mut cars: Query<(&Velocity, &CarTrack), With<Player>>,
+    mut cars: Query<(&Velocity, &CarTrack, &LapTimer), With<Player>>,
+    track_config: Res<TrackConfig>,
 ) {
-    for (velocity, car_track) in cars.iter_mut() {
+    for (velocity, car_track, lap_timer) in cars.iter_mut() {
         let mps = velocity.linvel.length();
         let kmph = mps * 3.6;
 
@@ -285,7 +333,13 @@ pub fn dash_speed_update_system(
         }
 
         if let Ok(mut text) = texts.p4().single_mut() {
-            text.0 = format!("lap {}", car_track.lap);
+            text.0 = match track_config.closed {
+                true => match lap_timer.delta {
+                    Some(delta) => format!("lap {} {:+.1}", car_track.lap, delta),
+                    None => format!("lap {} {:.1}s", car_track.lap, lap_timer.current),
+                },
+                false => format!("{:.2}s", car_track.stage_time),
+            };
         }
 
         // // Lap text
@@ -309,3 +363,63 @@ pub fn dash_speed_update_system(
         // texts.p4().single_mut().unwrap().0 = format!("{:.1}km/h", kmph);
     }
 }
+
+/// Session name with its clock or race laps, and the start lights during the countdown.
+pub fn dash_session_system(
+    mut cmd: Commands,
+    session: Res<Session>,
+    mut texts: Query<&mut Text, With<SessionText>>,
+    mut start_lights: Query<(Entity, &mut Visibility, Option<&Children>), With<StartLights>>,
+    mut lights: Query<(&StartLight, &mut BackgroundColor)>,
+) {
+    if let Ok(mut text) = texts.single_mut() {
+        let clock = |time: f32| format!("{}:{:02}", time as u32 / 60, time as u32 % 60);
+        let limit = |limit: Option<f32>| match limit {
+            Some(limit) => clock((limit - session.time).max(0.)),
+            None => clock(session.time),
+        };
+        let leader = session.cars.iter().map(|car| car.laps).max().unwrap_or(0);
+        text.0 = match session.state {
+            SessionState::Practice => format!("practice {}", limit(session.rules.practice)),
+            SessionState::Qualifying => format!("qualifying {}", limit(session.rules.qualifying)),
+            SessionState::Grid | SessionState::Countdown => "grid".to_string(),
+            SessionState::Racing => match session.laps() {
+                Some(laps) => format!("lap {}/{laps}", (leader + 1).min(laps)),
+                None => format!("race {}", limit(session.rules.time_limit)),
+            },
+            SessionState::Finished => "finished".to_string(),
+        };
+    }
+
+    let Ok((root, mut visibility, children)) = start_lights.single_mut() else {
+        return;
+    };
+    *visibility = match session.state {
+        SessionState::Countdown => Visibility::Inherited,
+        _ => Visibility::Hidden,
+    };
+    if children.map_or(0, |children| children.len()) != session.rules.lights as usize {
+        cmd.entity(root).despawn_related::<Children>();
+        for i in 0..session.rules.lights {
+            let light = cmd
+                .spawn((
+                    Node {
+                        width: Val::Px(START_LIGHT_SIZE),
+                        height: Val::Px(START_LIGHT_SIZE),
+                        border_radius: BorderRadius::MAX,
+                        ..default()
+                    },
+                    BackgroundColor(css::DARK_SLATE_GRAY.into()),
+                    StartLight(i),
+                ))
+                .id();
+            cmd.entity(root).add_child(light);
+        }
+    }
+    for (light, mut color) in lights.iter_mut() {
+        *color = BackgroundColor(match light.0 < session.lights {
+            true => css::RED.into(),
+            false => css::DARK_SLATE_GRAY.into(),
+        });
+    }
+}
@@ -1,14 +1,14 @@
+use crate::minimap::Minimap;
 use bevy::prelude::*;
 use bevy_garage_camera::CameraConfig;
-use bevy_garage_car::{Car, CarRes, CarWheels, Player};
-use bevy_garage_track::SpawnCarOnTrackEvent;
+use bevy_garage_car::{Car, CarRes, Player};
+use bevy_garage_track::{AdvanceSession, LoadTrack, RespawnCar, TrackRegistry, Weather};
 
 pub fn input_system(
     input: Res<ButtonInput<KeyCode>>,
     mut camera_config: ResMut<CameraConfig>,
-    mut cars: Query<(&mut Car, &mut CarWheels, Entity, &Transform), With<Player>>,
-    mut cmd: Commands,
-    mut car_spawn_events: MessageWriter<SpawnCarOnTrackEvent>,
+    mut cars: Query<(&mut Car, Entity, &Transform), With<Player>>,
+    mut respawn_events: MessageWriter<RespawnCar>,
     mut debug_ctx: ResMut<bevy_rapier3d::render::DebugRenderContext>,
     mut car_res: ResMut<CarRes>,
     #[cfg(feature = "nn")] mut dqn: ResMut<bevy_garage_nn::DqnResource>,
@@ -21,16 +21,9 @@ pub fn input_system(
         debug_ctx.enabled = !debug_ctx.enabled;
         car_res.show_rays = debug_ctx.enabled;
     }
-    for (mut car, mut wheels, e, _transform) in cars.iter_mut() {
+    for (mut car, e, _transform) in cars.iter_mut() {
         if input.just_pressed(KeyCode::Space) && input.pressed(KeyCode::ShiftLeft) {
-            cmd.entity(e).despawn();
-            wheels.despawn(&mut cmd);
-
-            car_spawn_events.write(SpawnCarOnTrackEvent {
-                player: true,
-                index: 0,
-                position: None,
-            });
+            respawn_events.write(RespawnCar { car: e });
         }
         if input.pressed(KeyCode::ArrowUp) {
             car.gas = 1.;
@@ -68,3 +61,43 @@ pub fn input_system(
         // }
     }
 }
+
+pub fn track_switch_input_system(
+    input: Res<ButtonInput<KeyCode>>,
+    track_registry: Res<TrackRegistry>,
+    mut load_track_events: MessageWriter<LoadTrack>,
+) {
+    if input.just_pressed(KeyCode::KeyT) {
+        load_track_events.write(LoadTrack {
+            name: track_registry.next().name.clone(),
+        });
+    }
+}
+
+/// Cycles the rain between dry, drizzle and heavy rain.
+pub fn weather_input_system(input: Res<ButtonInput<KeyCode>>, mut weather: ResMut<Weather>) {
+    if input.just_pressed(KeyCode::KeyP) {
+        weather.rain = match weather.rain {
+            r if r < 0.5 => 0.5,
+            r if r < 1. => 1.,
+            _ => 0.,
+        };
+    }
+}
+
+/// Switches the minimap between north up and turning with the player car.
+pub fn minimap_input_system(input: Res<ButtonInput<KeyCode>>, mut minimap: ResMut<Minimap>) {
+    if input.just_pressed(KeyCode::KeyM) {
+        minimap.rotate = !minimap.rotate;
+    }
+}
+
+/// Ends practice or qualifying early, or starts the next weekend after the race.
+pub fn session_input_system(
+    input: Res<ButtonInput<KeyCode>>,
+    mut advance_events: MessageWriter<AdvanceSession>,
+) {
+    if input.just_pressed(KeyCode::KeyG) {
+        advance_events.write(AdvanceSession);
+    }
+}
@@ -4,6 +4,7 @@ pub mod font;
 mod input;
 #[cfg(feature = "virtual_joystick")]
 pub mod joystick;
+mod minimap;
 mod spawn;
 use std::num::NonZeroUsize;
 
@@ -11,15 +12,22 @@ use bevy::{
     diagnostic::FrameTimeDiagnosticsPlugin, ecs::system::SystemParam,
     light::DirectionalLightShadowMap, prelude::*,
 };
-use bevy_garage_car::{aero_system, car_start_system, esp_system, CarRes, CarSet};
-use bevy_garage_light::{animate_light_direction, light_start_system};
-use bevy_garage_track::{track_polyline_start_system, SpawnCarOnTrackEvent, TrackPlugin};
+use bevy_garage_car::{aero_system, car_start_system, esp_system, Air, CarRes, CarSet};
+use bevy_garage_light::{
+    brake_lights_system, car_lights_system, day_night_system, headlights_system,
+    light_start_system, night_lights_system, time_of_day_system, TimeOfDay,
+};
+use bevy_garage_track::{
+    rubber_grip, session_grid_system, track_polyline_start_system, SessionPlugin, Surface,
+    TrackLoadedEvent, TrackPlugin, Weather, WheelSurface,
+};
 use bevy_rapier3d::plugin::WriteRapierContext;
 use bevy_rapier3d::prelude::*;
 use config::*;
 use dash::*;
 use font::*;
 use input::*;
+use minimap::*;
 use spawn::*;
 
 fn rapier_config_start_system(mut c: WriteRapierContext) {
@@ -52,6 +60,9 @@ pub fn car_app(app: &mut App) -> &mut App {
         .insert_resource(Config::default())
         .insert_resource(CarRes::default())
         .insert_resource(DirectionalLightShadowMap::default())
+        .init_resource::<TimeOfDay>()
+        .init_resource::<Air>()
+        .init_resource::<Minimap>()
         // .insert_resource(TimestepMode::Variable {
         //     max_dt: 1. / 60.,
         //     time_scale: 1.,
@@ -68,8 +79,9 @@ pub fn car_app(app: &mut App) -> &mut App {
         })
         .add_plugins((
             FrameTimeDiagnosticsPlugin::default(),
-            RapierPhysicsPlugin::<MyPhysicsHooks>::default(),
+            RapierPhysicsPlugin::<MyPhysicsHooks<'static, 'static>>::default(),
             TrackPlugin,
+            SessionPlugin,
             RapierDebugRenderPlugin {
                 enabled: false,
                 style: DebugRenderStyle {
@@ -84,12 +96,10 @@ pub fn car_app(app: &mut App) -> &mut App {
                 ..default()
             },
         ))
-        .add_message::<SpawnCarOnTrackEvent>()
         .add_systems(
             Startup,
             (
-                car_start_system.after(track_polyline_start_system),
-                spawn_car_start_system.after(car_start_system),
+                car_start_system,
                 light_start_system,
                 dash_start_system,
                 rapier_config_start_system,
@@ -98,13 +108,31 @@ pub fn car_app(app: &mut App) -> &mut App {
         .add_systems(
             Update,
             (
-                spawn_car_system,
+                spawn_car_start_system
+                    .run_if(on_message::<TrackLoadedEvent>.and(run_once))
+                    .after(track_polyline_start_system),
+                // the grid despawns the cars before they come back, their slots are free again
+                spawn_car_system
+                    .after(spawn_car_start_system)
+                    .after(session_grid_system),
                 aero_system.in_set(CarSet::Input),
                 input_system.in_set(CarSet::Input),
+                track_switch_input_system,
+                weather_input_system,
+                minimap_input_system,
+                session_input_system,
                 esp_system.in_set(CarSet::Esp).after(esp_run_after),
-                animate_light_direction,
+                time_of_day_system,
+                day_night_system.after(time_of_day_system),
+                night_lights_system.after(time_of_day_system),
+                car_lights_system,
+                brake_lights_system,
+                headlights_system,
                 dash_fps_system,
                 dash_speed_update_system,
+                dash_session_system,
+                minimap_start_system.run_if(on_message::<TrackLoadedEvent>),
+                minimap_system.after(minimap_start_system),
             ),
         );
 
@@ -121,36 +149,32 @@ pub fn car_app(app: &mut App) -> &mut App {
 }
 
 #[derive(SystemParam)]
-struct MyPhysicsHooks;
+struct MyPhysicsHooks<'w, 's> {
+    surfaces: Query<'w, 's, &'static Surface>,
+    wheels: Query<'w, 's, &'static WheelSurface>,
+    weather: Res<'w, Weather>,
+}
 
-impl BevyPhysicsHooks for MyPhysicsHooks {
+impl BevyPhysicsHooks for MyPhysicsHooks<'_, '_> {
     fn modify_solver_contacts(&self, context: ContactModificationContextView) {
-        // *context.raw.normal = -*context.raw.normal;
-        // println!("normal {:?}", context.raw.bodies);
-        // if !context.raw.solver_contacts.is_empty() {
-        //     context.raw.solver_contacts.swap_remove(0);
-        // }
-        // let manifold = context.raw.manifold;
-        // manifold.data;
-        // println!("solver_contacts={:?}", &context.raw.solver_contacts);
+        // zone, water and rubber under a wheel are sampled once a frame by wheel_surface_system
+        let wheel = [context.collider1(), context.collider2()]
+            .into_iter()
+            .find_map(|e| self.wheels.get(e).ok());
+        let zone = wheel.and_then(|wheel| wheel.zone);
+        let surface = |e: Entity| self.surfaces.get(e).ok().map(|s| s.in_zone(zone));
+        let wetness = wheel.map_or(self.weather.wetness, |wheel| wheel.wetness);
+        let (surface1, surface2) = (surface(context.collider1()), surface(context.collider2()));
+        let Some(friction) = Surface::pair_friction(surface1, surface2, wetness) else {
+            return;
+        };
+        // rubber laid on the asphalt grips better
+        let rubber = match [surface1, surface2].contains(&Some(Surface::Asphalt)) {
+            true => wheel.map_or(1., |wheel| rubber_grip(wheel.rubber, wetness)),
+            false => 1.,
+        };
         for solver_contact in &mut *context.raw.solver_contacts {
-            // println!("solver {:?}", solver_contact.tangent_velocity);
-            // solver_contact.warmstart_impulse = 0.0;
-            // solver_contact.warmstart_tangent_impulse =
-            // bevy_rapier3d::rapier::math::TangentImpulse::zeros();
-
-            // solver_contact.friction = 0.3;
-            // solver_contact.restitution = 0.1;
-            // solver_contact.tangent_velocity.x = 10.0;
+            solver_contact.friction = friction * self.weather.grip() * rubber;
         }
-
-        // Use the persistent user-data to count the number of times
-        // contact modification was called for this contact manifold
-        // since its creation.
-        // *context.raw.user_data += 1;
-        // println!(
-        //     "Contact manifold has been modified {} times since its creation.",
-        //     *context.raw.user_data
-        // );
     }
 }
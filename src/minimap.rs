@@ -0,0 +1,155 @@
+use bevy::{
+    asset::RenderAssetUsages,
+    render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+};
+use bevy::{color::palettes::css, prelude::*};
+use bevy_garage_car::Player;
+use bevy_garage_track::{CarTrack, TrackAsset, TrackHandle, TrackMap};
+
+const MINIMAP_SIZE: f32 = 200.;
+const MINIMAP_RESOLUTION: u32 = 256;
+const MARKER_SIZE: f32 = 8.;
+const PLACE_COLORS: [Srgba; 3] = [css::GOLD, css::SILVER, css::PERU];
+
+#[derive(Resource, Default)]
+pub struct Minimap {
+    pub map: TrackMap,
+    /// Turns the map so the player car heads up instead of keeping north up.
+    pub rotate: bool,
+}
+
+#[derive(Component)]
+pub struct MinimapRoot;
+
+/// Map image and car markers, turned together when the map rotates.
+#[derive(Component)]
+pub struct MinimapRotor;
+
+#[derive(Component)]
+pub struct MinimapMarker(pub Entity);
+
+pub fn minimap_start_system(
+    mut cmd: Commands,
+    mut minimap: ResMut<Minimap>,
+    mut images: ResMut<Assets<Image>>,
+    track_handle: Res<TrackHandle>,
+    tracks: Res<Assets<TrackAsset>>,
+    roots: Query<Entity, With<MinimapRoot>>,
+) {
+    let Some(track_asset) = tracks.get(&track_handle.0) else {
+        return;
+    };
+    for root in roots.iter() {
+        cmd.entity(root).despawn();
+    }
+    minimap.map = TrackMap::new(track_asset);
+    let image = images.add(Image::new(
+        Extent3d {
+            width: MINIMAP_RESOLUTION,
+            height: MINIMAP_RESOLUTION,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        minimap.map.rasterize(MINIMAP_RESOLUTION),
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::RENDER_WORLD,
+    ));
+
+    cmd.spawn((
+        Node {
+            position_type: PositionType::Absolute,
+            left: Val::Px(8.),
+            bottom: Val::Px(8.),
+            width: Val::Px(MINIMAP_SIZE),
+            height: Val::Px(MINIMAP_SIZE),
+            overflow: Overflow::clip(),
+            ..default()
+        },
+        BackgroundColor(Color::srgba(0.15, 0.15, 0.15, 0.5)),
+        MinimapRoot,
+    ))
+    .with_children(|parent| {
+        parent.spawn((
+            Node {
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                ..default()
+            },
+            ImageNode::new(image),
+            UiTransform::default(),
+            MinimapRotor,
+        ));
+    });
+}
+
+/// Moves a marker for every car on the track and turns the map with the player.
+pub fn minimap_system(
+    mut cmd: Commands,
+    minimap: Res<Minimap>,
+    mut rotors: Query<(Entity, &mut UiTransform), With<MinimapRotor>>,
+    cars: Query<(Entity, &Transform, &CarTrack, Has<Player>)>,
+    mut markers: Query<(Entity, &MinimapMarker, &mut Node, &mut BackgroundColor)>,
+) {
+    let Ok((rotor, mut rotor_transform)) = rotors.single_mut() else {
+        return;
+    };
+    let count = cars.iter().len();
+    for (marker, car, mut node, mut color) in markers.iter_mut() {
+        let Ok((_, transform, car_track, _)) = cars.get(car.0) else {
+            cmd.entity(marker).despawn();
+            continue;
+        };
+        let uv = minimap.map.uv(transform.translation.xz());
+        node.left = Val::Percent(uv.x * 100.);
+        node.top = Val::Percent(uv.y * 100.);
+        // place counts from the back of the field
+        let position = count - 1 - car_track.place.min(count - 1);
+        *color = BackgroundColor(match PLACE_COLORS.get(position) {
+            Some(place_color) => (*place_color).into(),
+            None => css::DARK_GRAY.into(),
+        });
+    }
+    for (car, _, _, player) in cars.iter() {
+        if markers.iter().any(|(_, marker, _, _)| marker.0 == car) {
+            continue;
+        }
+        let size = if player {
+            MARKER_SIZE * 1.5
+        } else {
+            MARKER_SIZE
+        };
+        let marker = cmd
+            .spawn((
+                Node {
+                    position_type: PositionType::Absolute,
+                    width: Val::Px(size),
+                    height: Val::Px(size),
+                    margin: UiRect {
+                        left: Val::Px(-size / 2.),
+                        top: Val::Px(-size / 2.),
+                        ..default()
+                    },
+                    ..default()
+                },
+                BackgroundColor(css::DARK_GRAY.into()),
+                MinimapMarker(car),
+            ))
+            .id();
+        if player {
+            cmd.entity(marker)
+                .insert(Outline::new(Val::Px(2.), Val::ZERO, Color::WHITE));
+        }
+        cmd.entity(rotor).add_child(marker);
+    }
+
+    let heading = cars
+        .iter()
+        .find(|(_, _, _, player)| *player)
+        .map(|(_, transform, _, _)| (transform.rotation * Vec3::Z).xz())
+        .filter(|_| minimap.rotate);
+    rotor_transform.rotation = match heading {
+        // map y is world z, the car heads up once turned back by its clockwise angle from -Z
+        Some(forward) => Rot2::radians(-forward.x.atan2(-forward.y)),
+        None => Rot2::IDENTITY,
+    };
+}
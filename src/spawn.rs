@@ -1,40 +1,61 @@
+use crate::config::Config;
 use bevy::prelude::*;
 use bevy_garage_car::CarRes;
-use bevy_garage_track::{spawn_car_on_track, SpawnCarOnTrackEvent, TrackConfig};
+use bevy_garage_track::{
+    spawn_car_on_track, spawn_transform, CarTrack, RollingStart, Session, SessionState,
+    SpawnCarOnTrackEvent, SpawnPosition, StartKind, TrackConfig,
+};
 
-pub fn spawn_car_start_system(mut car_spawn_events: MessageWriter<SpawnCarOnTrackEvent>) {
+pub fn spawn_car_start_system(
+    config: Res<Config>,
+    mut car_spawn_events: MessageWriter<SpawnCarOnTrackEvent>,
+) {
     car_spawn_events.write(SpawnCarOnTrackEvent {
         player: true,
         index: 0,
-        position: Some(0.),
+        count: config.cars_count,
+        position: SpawnPosition::Grid(0),
     });
 }
 
 pub fn spawn_car_system(
     mut events: MessageReader<SpawnCarOnTrackEvent>,
     mut cmd: Commands,
-    track_config: ResMut<TrackConfig>,
-    car_res: ResMut<CarRes>,
+    track_config: Res<TrackConfig>,
+    car_res: Res<CarRes>,
+    session: Res<Session>,
+    cars: Query<&Transform, With<CarTrack>>,
 ) {
+    let mut occupied: Vec<Vec3> = cars.iter().map(|t| t.translation).collect();
+    let layout = &session.rules.grid;
     for spawn_event in events.read() {
         dbg!(spawn_event);
+        for i in 0..spawn_event.count {
+            let position = match spawn_event.position {
+                SpawnPosition::Grid(slot) => SpawnPosition::Grid(slot + i),
+                position => position,
+            };
+            let (transform, init_meters) =
+                spawn_transform(&track_config, layout, position, &occupied);
+            occupied.push(transform.translation);
 
-        let (transform, init_meters) = if let Some(init_meters) = spawn_event.position {
-            let (translate, quat) = track_config.get_transform_by_meter(init_meters);
-            let transform = Transform::from_translation(translate).with_rotation(quat);
-            (transform, init_meters)
-        } else {
-            track_config.get_transform_random()
-        };
-
-    spawn_car_on_track(
-        &mut cmd,
-        &car_res.car_scene.as_ref().unwrap(),
-        &car_res.wheel_scene.as_ref().unwrap(),
-        spawn_event.player,
-        transform,
-        spawn_event.index,
-        init_meters,
-    );
+            let car_id = spawn_car_on_track(
+                &mut cmd,
+                car_res.car_scene.as_ref().unwrap(),
+                car_res.wheel_scene.as_ref().unwrap(),
+                spawn_event.player && i == 0,
+                transform,
+                spawn_event.index + i,
+                init_meters,
+            );
+            // the formation only rolls when the grid forms for the race
+            if matches!(position, SpawnPosition::Grid(_))
+                && layout.start == StartKind::Rolling
+                && session.state == SessionState::Grid
+            {
+                cmd.entity(car_id)
+                    .insert(RollingStart(layout.rolling_speed / 3.6));
+            }
+        }
     }
 }
@@ -1,22 +1,167 @@
+use bevy_garage_track::{
+    racing_line, validate_track, RacingLineSettings, ScatterRule, SceneryKind, Track, TrackAsset,
+    TrackDirection, TrackMap, TrackPoint,
+};
 use obj::*;
+use ron::extensions::Extensions;
 use std::fs::File;
 use std::io::BufReader;
 use std::io::Write;
+use std::process::ExitCode;
 
-fn main() {
+fn read_track(path: &str) -> Result<TrackAsset, String> {
+    let options = ron::Options::default().with_default_extension(Extensions::IMPLICIT_SOME);
+    let bytes = std::fs::read(path).map_err(|e| e.to_string())?;
+    options
+        .from_bytes::<TrackAsset>(&bytes)
+        .map_err(|e| e.to_string())
+}
+
+/// Prints the geometry issues of every track file and its branches.
+fn validate(paths: &[String]) -> ExitCode {
+    let mut failed = false;
+    for path in paths {
+        let track = match read_track(path) {
+            Ok(track) => track,
+            Err(e) => {
+                println!("{path}: {e}");
+                failed = true;
+                continue;
+            }
+        };
+        let mut issues: Vec<String> = validate_track(&track)
+            .iter()
+            .map(|issue| format!("{path}: {issue}"))
+            .collect();
+        for branch in track.branches.iter() {
+            issues.extend(
+                validate_track(&track.branch_asset(branch))
+                    .iter()
+                    .map(|issue| format!("{path} branch {}: {issue}", branch.name)),
+            );
+        }
+        match issues.is_empty() {
+            true => println!("{path}: ok"),
+            false => issues.iter().for_each(|issue| println!("{issue}")),
+        }
+        failed |= !issues.is_empty();
+    }
+    match failed {
+        true => ExitCode::FAILURE,
+        false => ExitCode::SUCCESS,
+    }
+}
+
+/// Writes the racing line of a track file as csv, one row per track point.
+fn export_racing_line(args: &[String]) -> ExitCode {
+    let Some(path) = args.iter().find(|arg| !arg.starts_with("--")) else {
+        println!("usage: racing-line <track.ron> [--min-time]");
+        return ExitCode::FAILURE;
+    };
+    let track = match read_track(path) {
+        Ok(track) => track,
+        Err(e) => {
+            println!("{path}: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let settings = RacingLineSettings {
+        min_time: args.iter().any(|arg| arg == "--min-time"),
+        ..Default::default()
+    };
+    let line = racing_line(&Track::new(&track), &settings);
+    println!("# {}, lap time {:.2}s", track.name, line.lap_time);
+    println!("meters,offset,x,y,z,speed_kmh");
+    for (i, p) in line.positions.iter().enumerate() {
+        println!(
+            "{:.2},{:.3},{:.3},{:.3},{:.3},{:.1}",
+            line.distances[i],
+            line.offsets[i],
+            p.x,
+            p.y,
+            p.z,
+            line.speeds[i] * 3.6
+        );
+    }
+    ExitCode::SUCCESS
+}
+
+/// Renders the map of a track file with sectors and corner numbers to svg.
+fn export_svg(args: &[String]) -> ExitCode {
+    let (Some(path), out) = (args.first(), args.get(1)) else {
+        println!("usage: svg <track.ron> [out.svg]");
+        return ExitCode::FAILURE;
+    };
+    let track = match read_track(path) {
+        Ok(track) => track,
+        Err(e) => {
+            println!("{path}: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let out = out
+        .cloned()
+        .unwrap_or_else(|| format!("{}.svg", track.name));
+    match std::fs::write(&out, TrackMap::new(&track).to_svg(1024.)) {
+        Ok(()) => {
+            println!("{out}");
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            println!("{out}: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().is_some_and(|arg| arg == "validate") {
+        return validate(&args[1..]);
+    }
+    if args.first().is_some_and(|arg| arg == "racing-line") {
+        return export_racing_line(&args[1..]);
+    }
+    if args.first().is_some_and(|arg| arg == "svg") {
+        return export_svg(&args[1..]);
+    }
     let polyline_buf = BufReader::new(File::open("assets/track-polyline.obj").unwrap());
     let model = raw::parse_obj(polyline_buf).unwrap();
+    let track = TrackAsset {
+        name: "default".to_string(),
+        width: 5.,
+        runoff: 2.5,
+        start: [0., 0., 0.],
+        open: false,
+        finish: None,
+        sectors: vec![],
+        direction: TrackDirection::Forward,
+        points: model
+            .positions
+            .iter()
+            .map(|p| TrackPoint::new([p.0, p.1, p.2]))
+            .collect(),
+        branches: vec![],
+        surfaces: vec![],
+        kerbs: Default::default(),
+        scenery: vec![],
+        scatter: vec![ScatterRule {
+            kind: SceneryKind::Tree,
+            density: 2.,
+            from_wall: 10.,
+            depth: 50.,
+            seed: 1,
+        }],
+        barriers: vec![],
+        session: None,
+    };
     let pretty_config = ron::ser::PrettyConfig::default()
         .indentor("  ".to_string())
-        .new_line("\n".to_string());
-    let pos_ron = ron::ser::to_string_pretty(&model.positions, pretty_config).unwrap();
-    File::create(format!("assets/track-positions.ron"))
-        .and_then(|mut file| file.write(pos_ron.as_bytes()))
-        .expect("Error while writing scene to file");
-    // let positions = model.positions;
+        .new_line("\n".to_string())
+        .depth_limit(2);
+    let track_ron = ron::ser::to_string_pretty(&track, pretty_config).unwrap();
+    File::create("assets/tracks/default.track.ron")
+        .and_then(|mut file| file.write(track_ron.as_bytes()))
+        .expect("Error while writing track to file");
+    ExitCode::SUCCESS
 }
-
-// let obj_path = "assets/track-polyline.obj";
-// let polyline_buf = BufReader::new(File::open(obj_path).unwrap());
-// let model = raw::parse_obj(polyline_buf).unwrap();
-// let positions = model.positions;
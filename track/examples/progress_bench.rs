@@ -0,0 +1,186 @@
+//! Compares the per-frame track queries of cars with full polyline projections against the
+//! warm-started searches the systems use: car progress and branches in `progress_system`,
+//! wheel edges in `track_limits_system`, and wheel surface, water and rubber in
+//! `wheel_surface_system`.
+//!
+//! cargo run --release -p bevy_garage_track --example progress_bench
+
+use bevy::ecs::system::RunSystemOnce;
+use bevy::prelude::*;
+use bevy_garage_track::{
+    project_on_polyline, track_coordinates, track_polyline_start_system, TrackAsset, TrackConfig,
+    TrackHandle, Weather,
+};
+use ron::extensions::Extensions;
+use std::time::{Duration, Instant};
+
+const FRAMES: usize = 600;
+const SPEED: f32 = 50.;
+const DT: f32 = 1. / 60.;
+const KERB_WIDTH: f32 = 1.;
+/// Wheel offsets from the car, left and forward.
+const WHEELS: [(f32, f32); 4] = [(0.8, 1.3), (-0.8, 1.3), (0.8, -1.3), (-0.8, -1.3)];
+
+fn track_config(name: &str) -> TrackConfig {
+    let path = format!(
+        "{}/../assets/tracks/{name}.track.ron",
+        env!("CARGO_MANIFEST_DIR")
+    );
+    let options = ron::Options::default().with_default_extension(Extensions::IMPLICIT_SOME);
+    let asset: TrackAsset = options
+        .from_bytes(&std::fs::read(path).expect("track file"))
+        .expect("track asset");
+    let mut world = World::new();
+    let mut tracks = Assets::<TrackAsset>::default();
+    let handle = tracks.add(asset);
+    world.insert_resource(tracks);
+    world.insert_resource(TrackHandle(handle));
+    world.insert_resource(TrackConfig::default());
+    world
+        .run_system_once(track_polyline_start_system)
+        .expect("track polyline");
+    world.remove_resource::<TrackConfig>().unwrap()
+}
+
+/// Cars spread over the lap, weaving across the road, and their wheels.
+fn car_position(
+    track_config: &TrackConfig,
+    car: usize,
+    cars: usize,
+    frame: usize,
+) -> (Vec3, [Vec3; 4]) {
+    let meters = track_config.track_length * car as f32 / cars as f32 + SPEED * DT * frame as f32;
+    let (_, quat) = track_config.get_transform_by_meter(meters);
+    let weave = 3. * (frame as f32 * 0.05 + car as f32).sin();
+    let p = track_config.position_at(meters) + quat.mul_vec3(Vec3::X) * weave;
+    let wheels = WHEELS.map(|(left, forward)| p + quat.mul_vec3(Vec3::new(left, 0., forward)));
+    (p, wheels)
+}
+
+/// Hints kept between frames, like `CarTrack` and `WheelSurface` do.
+#[derive(Clone, Default)]
+struct Hints {
+    car: Option<usize>,
+    branches: Vec<Option<usize>>,
+    wheels: [Option<usize>; 4],
+}
+
+/// Main distance of a car and how many of its wheels are within the edges.
+type Query = (f32, usize);
+
+fn full(track_config: &TrackConfig, weather: &Weather, p: Vec3, wheels: &[Vec3; 4]) -> Query {
+    let polyline = track_config.polyline.as_ref().unwrap();
+    let projection = project_on_polyline(polyline, &track_config.segments, p);
+    let mut offset = projection.offset;
+    for branch in track_config.branches.iter() {
+        offset = offset.min(project_on_polyline(&branch.polyline, &branch.segments, p).offset);
+    }
+    std::hint::black_box(offset);
+    let mut within = 0;
+    for wheel in wheels.iter() {
+        if track_config.is_within_edges(*wheel, KERB_WIDTH, None) {
+            within += 1;
+        }
+        let projection = project_on_polyline(polyline, &track_config.segments, *wheel);
+        std::hint::black_box((
+            track_config.zone_surface(projection.distance),
+            weather.wetness_at(Some(projection.offset)),
+            track_coordinates(track_config, &projection, *wheel),
+        ));
+    }
+    (projection.distance, within)
+}
+
+fn warm(
+    track_config: &TrackConfig,
+    weather: &Weather,
+    p: Vec3,
+    wheels: &[Vec3; 4],
+    hints: &mut Hints,
+) -> Query {
+    let projection = track_config.project_near(p, hints.car).unwrap();
+    hints.car = Some(projection.segment);
+    let mut offset = projection.offset;
+    hints.branches.resize(track_config.branches.len(), None);
+    for (branch, hint) in track_config.branches.iter().zip(hints.branches.iter_mut()) {
+        if !branch.spans(projection.distance, track_config.track_length) {
+            continue;
+        }
+        let projection = branch.project_near(p, *hint);
+        *hint = Some(projection.segment);
+        offset = offset.min(projection.offset);
+    }
+    std::hint::black_box(offset);
+    let mut within = 0;
+    for (wheel, hint) in wheels.iter().zip(hints.wheels.iter_mut()) {
+        if track_config.is_within_edges(*wheel, KERB_WIDTH, hints.car) {
+            within += 1;
+        }
+        let projection = track_config.project_near(*wheel, *hint).unwrap();
+        *hint = Some(projection.segment);
+        std::hint::black_box((
+            track_config.zone_surface(projection.distance),
+            weather.wetness_at(Some(projection.offset)),
+            track_coordinates(track_config, &projection, *wheel),
+        ));
+    }
+    (projection.distance, within)
+}
+
+fn main() {
+    let weather = Weather::default();
+    for name in ["oval", "default"] {
+        let track_config = track_config(name);
+        let polyline = track_config.polyline.as_ref().unwrap();
+        println!(
+            "{name}: {} segments, {} branches, {:.0}m",
+            polyline.num_segments(),
+            track_config.branches.len(),
+            track_config.track_length
+        );
+        for cars in [10, 100, 500] {
+            let mut full_time = Duration::ZERO;
+            let mut warm_time = Duration::ZERO;
+            let mut hints: Vec<Hints> = vec![Hints::default(); cars];
+            let mut mismatches = 0;
+            for frame in 0..FRAMES {
+                let positions: Vec<(Vec3, [Vec3; 4])> = (0..cars)
+                    .map(|car| car_position(&track_config, car, cars, frame))
+                    .collect();
+
+                let start = Instant::now();
+                let expected: Vec<Query> = positions
+                    .iter()
+                    .map(|(p, wheels)| full(&track_config, &weather, *p, wheels))
+                    .collect();
+                full_time += start.elapsed();
+
+                let start = Instant::now();
+                let mut board: Vec<(Query, usize)> = Vec::with_capacity(cars);
+                for (car, (p, wheels)) in positions.iter().enumerate() {
+                    let query = warm(&track_config, &weather, *p, wheels, &mut hints[car]);
+                    board.push((query, car));
+                }
+                board.sort_by(|a, b| a.0 .0.total_cmp(&b.0 .0));
+                warm_time += start.elapsed();
+
+                for ((distance, within), car) in board.iter() {
+                    // the start of the first segment and the end of the last are the same point
+                    let error = (distance - expected[*car].0).abs();
+                    if error.min(track_config.track_length - error) > 0.01
+                        || *within != expected[*car].1
+                    {
+                        mismatches += 1;
+                    }
+                }
+            }
+            let per_frame = |d: Duration| d.as_secs_f64() * 1e6 / FRAMES as f64;
+            println!(
+                "  {cars:>4} cars: full {:>8.1}us/frame, warm start {:>8.1}us/frame, {:.1}x, {mismatches} mismatches",
+                per_frame(full_time),
+                per_frame(warm_time),
+                full_time.as_secs_f64() / warm_time.as_secs_f64(),
+            );
+        }
+    }
+}
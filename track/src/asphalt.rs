@@ -1,4 +1,6 @@
-use super::{MaterialHandle, Track, TrackRoad};
+use super::{
+    rubber_texture_v, CellLod, CullCell, MaterialHandle, Surface, Track, TrackEntity, TrackRoad,
+};
 use bevy::asset::RenderAssetUsages;
 use bevy::camera::primitives::{Aabb, MeshAabb};
 use bevy::light::NotShadowCaster;
@@ -8,106 +10,55 @@ use bevy_garage_car::STATIC_GROUP;
 use bevy_rapier3d::{na::Point3, prelude::*, rapier::prelude::ColliderShape};
 
 #[derive(Component, Debug)]
-pub struct AsphaltCell {
-    pub is_color: bool,
-}
-
-#[derive(Debug)]
-pub struct AsphaltBlock {
-    vertices: Vec<[f32; 3]>,
-    normals: Vec<[f32; 3]>,
-    indices: Vec<u32>,
-    uvs: Vec<[f32; 2]>,
-}
+pub struct AsphaltCell;
 
-const BLOCK_SPAN: usize = 1;
+/// Track segments per road block.
+const BLOCK_SPAN: usize = 8;
+/// Far blocks keep every this many cross sections of the road.
+const BLOCK_FAR_STEP: usize = 4;
 
 pub fn spawn_road(
     handled_materials: &Res<MaterialHandle>,
     cmd: &mut Commands,
     meshes: &mut ResMut<Assets<Mesh>>,
     track: &Track,
+    rubber: bool,
 ) -> Aabb {
-    let mut blocks_indexes: Vec<Vec<usize>> = vec![];
-    for i in 0..track.points.len() {
-        let block_i = i % BLOCK_SPAN;
-        if block_i == 0 {
-            if i + 1 < track.points.len() {
-                let ilast = i + 1 + BLOCK_SPAN;
-                blocks_indexes.push((i..ilast).collect());
-            }
+    let points_len = track.points.len();
+    // the main track maps the rubber texture along its length, branches stay clean
+    let rubber_v: Option<Vec<f32>> = rubber.then(|| {
+        let mut meters = vec![0.];
+        for w in track.points.windows(2) {
+            meters.push(meters[meters.len() - 1] + w[0].distance(w[1]));
         }
-    }
-    // dbg!(&blocks_indexes);
-
-    let mut blocks: Vec<AsphaltBlock> = Vec::new();
-    for block_indexes in blocks_indexes {
-        let mut vertices: Vec<[f32; 3]> = vec![];
-        let mut normals: Vec<[f32; 3]> = vec![];
-        let mut indices: Vec<u32> = vec![];
-        let mut uvs: Vec<[f32; 2]> = vec![];
-
-        for (block_i, track_i) in block_indexes.iter().enumerate() {
-            let left = track.left[*track_i];
-            let right = track.right[*track_i];
-            vertices.push(left.to_array());
-            vertices.push(right.to_array());
-            normals.push(Vec3::Y.into());
-            normals.push(Vec3::Y.into());
-            let x = 50.;
-            uvs.push([left.x / x, left.z / x]);
-            uvs.push([right.x / x, right.z / x]);
-
-            let last: bool = block_i == BLOCK_SPAN;
-            if !last {
-                let ix2: u32 = block_i as u32 * 2;
-                let (i1, i2) = ([ix2, ix2 + 1, ix2 + 2], [ix2 + 2, ix2 + 1, ix2 + 3]);
-                // 2---3
-                // | \ |
-                // 0---1
-                // 1st triangle 0 1 2
-                // 2nd triangle 2 1 3
-                indices.extend(i1);
-                indices.extend(i2);
-            }
-        }
-
-        blocks.push(AsphaltBlock {
-            vertices,
-            normals,
-            indices,
-            uvs,
-        });
-    }
-    for block in blocks.iter() {
-        let mut mesh = Mesh::new(
-            PrimitiveTopology::TriangleList,
-            RenderAssetUsages::RENDER_WORLD,
-        );
-        let tr: Vec3 = block.vertices[0].into();
-        let vertices: Vec<[f32; 3]> = block
-            .vertices
+        let length = meters[meters.len() - 1];
+        meters
+            .iter()
+            .map(|m| rubber_texture_v(*m, length))
+            .collect()
+    });
+    for i in (0..points_len.saturating_sub(1)).step_by(BLOCK_SPAN) {
+        let block_indexes: Vec<usize> = (i..=(i + BLOCK_SPAN).min(points_len - 1)).collect();
+        let far_indexes: Vec<usize> = block_indexes
             .iter()
-            .map(|v| (Vec3::new(v[0], v[1], v[2]) - tr).to_array())
+            .enumerate()
+            .filter(|(k, _)| k % BLOCK_FAR_STEP == 0 || k + 1 == block_indexes.len())
+            .map(|(_, track_i)| *track_i)
             .collect();
-        mesh.insert_attribute(
-            Mesh::ATTRIBUTE_POSITION,
-            VertexAttributeValues::from(vertices),
-        );
-        mesh.insert_attribute(
-            Mesh::ATTRIBUTE_NORMAL,
-            VertexAttributeValues::from(block.normals.clone()),
-        );
-        mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, block.uvs.clone());
-        mesh.insert_indices(Indices::U32(block.indices.clone()));
-        mesh.generate_tangents().unwrap();
-
+        let tr = track.left[i];
+        let lod = CellLod {
+            near: meshes.add(block_mesh(track, &block_indexes, tr, rubber_v.as_deref())),
+            far: meshes.add(block_mesh(track, &far_indexes, tr, rubber_v.as_deref())),
+        };
         cmd.spawn((
-            Mesh3d(meshes.add(mesh.clone())),
+            Mesh3d(lod.near.clone()),
             MeshMaterial3d(handled_materials.asphalt.clone()),
             Transform::from_translation(tr),
             NotShadowCaster,
-            AsphaltCell { is_color: false },
+            AsphaltCell,
+            CullCell::default(),
+            lod,
+            TrackEntity,
         ));
     }
 
@@ -139,6 +90,7 @@ pub fn spawn_road(
 
     cmd.spawn((
         TrackRoad,
+        TrackEntity,
         Collider::from(
             ColliderShape::trimesh(
                 track_vertices
@@ -157,6 +109,64 @@ pub fn spawn_road(
             ..default()
         },
         Restitution::coefficient(0.1),
+        Surface::Asphalt,
     ));
     aabb
 }
+
+/// Road strip through the given cross sections, relative to the block origin,
+/// with the rubber texture coordinate of every cross section as the second uv set.
+fn block_mesh(
+    track: &Track,
+    track_indexes: &[usize],
+    origin: Vec3,
+    rubber_v: Option<&[f32]>,
+) -> Mesh {
+    let mut vertices: Vec<[f32; 3]> = vec![];
+    let mut normals: Vec<[f32; 3]> = vec![];
+    let mut indices: Vec<u32> = vec![];
+    let mut uvs: Vec<[f32; 2]> = vec![];
+    for (block_i, track_i) in track_indexes.iter().enumerate() {
+        let left = track.left[*track_i];
+        let right = track.right[*track_i];
+        vertices.push((left - origin).to_array());
+        vertices.push((right - origin).to_array());
+        normals.push(track.normals[*track_i].into());
+        normals.push(track.normals[*track_i].into());
+        let x = 50.;
+        uvs.push([left.x / x, left.z / x]);
+        uvs.push([right.x / x, right.z / x]);
+
+        if block_i + 1 < track_indexes.len() {
+            let ix2: u32 = block_i as u32 * 2;
+            // 2---3
+            // | \ |
+            // 0---1
+            // 1st triangle 0 1 2
+            // 2nd triangle 2 1 3
+            indices.extend([ix2, ix2 + 1, ix2 + 2]);
+            indices.extend([ix2 + 2, ix2 + 1, ix2 + 3]);
+        }
+    }
+    let mut mesh = Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::RENDER_WORLD,
+    );
+    mesh.insert_attribute(
+        Mesh::ATTRIBUTE_POSITION,
+        VertexAttributeValues::from(vertices),
+    );
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, VertexAttributeValues::from(normals));
+    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+    if let Some(rubber_v) = rubber_v {
+        // across the road from the right edge at 0 to the left at 1
+        let rubber_uvs: Vec<[f32; 2]> = track_indexes
+            .iter()
+            .flat_map(|i| [[1., rubber_v[*i]], [0., rubber_v[*i]]])
+            .collect();
+        mesh.insert_attribute(Mesh::ATTRIBUTE_UV_1, rubber_uvs);
+    }
+    mesh.insert_indices(Indices::U32(indices));
+    mesh.generate_tangents().unwrap();
+    mesh
+}
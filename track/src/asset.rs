@@ -0,0 +1,342 @@
+use crate::{
+    BarrierZone, KerbOverride, KerbSettings, ScatterRule, SceneryItem, SessionRules, SplineSample,
+    SurfaceZone, TrackRegistry, TrackSpline,
+};
+use bevy::asset::{io::Reader, AssetLoader, LoadContext};
+use bevy::prelude::*;
+use ron::extensions::Extensions;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+pub const DEFAULT_TRACK_PATH: &str = "tracks/default.track.ron";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TrackDirection {
+    #[default]
+    Forward,
+    Reverse,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrackPoint {
+    pub position: [f32; 3],
+    /// Roll of the road surface in degrees, positive raises the left edge.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub banking: f32,
+    /// Control values, interpolated along the track between points that set them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub width: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub runoff_left: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub runoff_right: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kerb: Option<KerbOverride>,
+}
+
+impl TrackPoint {
+    pub fn new(position: [f32; 3]) -> Self {
+        Self {
+            position,
+            banking: 0.,
+            width: None,
+            runoff_left: None,
+            runoff_right: None,
+            kerb: None,
+        }
+    }
+}
+
+fn is_zero(value: &f32) -> bool {
+    *value == 0.
+}
+
+fn default_runoff() -> f32 {
+    2.5
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct SpeedLimitZone {
+    /// Meters along the branch where the limit starts and ends.
+    pub from: f32,
+    pub to: f32,
+    pub kmh: f32,
+}
+
+/// Road that splits off the main loop and rejoins it, like a pit lane or a shortcut.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrackBranch {
+    pub name: String,
+    /// Distance from the centerline to the road edge, the main track width when not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub width: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub speed_limit: Option<SpeedLimitZone>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pit_box: Option<[f32; 3]>,
+    /// Split point first and rejoin point last, both on the main centerline.
+    pub points: Vec<TrackPoint>,
+}
+
+#[derive(Asset, TypePath, Debug, Clone, Serialize, Deserialize)]
+pub struct TrackAsset {
+    pub name: String,
+    /// Distance from the centerline to the road edge.
+    pub width: f32,
+    /// Distance from the road edge to the wall.
+    #[serde(default = "default_runoff")]
+    pub runoff: f32,
+    pub start: [f32; 3],
+    /// Point-to-point track such as a hill climb or rally stage.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub open: bool,
+    /// Finish line of an open track, the last point when not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub finish: Option<[f32; 3]>,
+    /// Sector boundaries, the lap is split in thirds when empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sectors: Vec<[f32; 3]>,
+    #[serde(default)]
+    pub direction: TrackDirection,
+    pub points: Vec<TrackPoint>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub branches: Vec<TrackBranch>,
+    /// Gravel traps and other run-off surfaces, grass everywhere else.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub surfaces: Vec<SurfaceZone>,
+    #[serde(default)]
+    pub kerbs: KerbSettings,
+    /// Trackside objects placed one by one.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub scenery: Vec<SceneryItem>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub scatter: Vec<ScatterRule>,
+    /// Barrier types along the walls, concrete everywhere else.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub barriers: Vec<BarrierZone>,
+    /// Race weekend rules, an open practice and a short race when not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session: Option<SessionRules>,
+}
+
+impl TrackAsset {
+    /// Spline through the control points in driving direction.
+    pub fn spline(&self) -> TrackSpline {
+        TrackSpline::new(
+            self.ordered(self.points.iter().map(|p| p.position.into()).collect()),
+            !self.open,
+        )
+    }
+    /// Spline samples the track is built from, closed tracks repeat the first sample.
+    pub fn samples(&self) -> Vec<SplineSample> {
+        self.spline().resample()
+    }
+    /// Centerline in driving direction, resampled along the spline.
+    pub fn centerline(&self) -> Vec<Vec3> {
+        self.samples().iter().map(|s| s.position).collect()
+    }
+    /// Branch geometry as an open track of its own.
+    pub fn branch_asset(&self, branch: &TrackBranch) -> TrackAsset {
+        TrackAsset {
+            name: branch.name.clone(),
+            width: branch.width.unwrap_or(self.width),
+            runoff: self.runoff,
+            start: branch
+                .points
+                .first()
+                .map(|p| p.position)
+                .unwrap_or_default(),
+            open: true,
+            finish: None,
+            sectors: vec![],
+            direction: self.direction,
+            points: branch.points.clone(),
+            branches: vec![],
+            surfaces: vec![],
+            kerbs: self.kerbs.clone(),
+            scenery: vec![],
+            scatter: vec![],
+            barriers: vec![],
+            session: None,
+        }
+    }
+    /// Finish line position, the end of the centerline unless set.
+    pub fn finish_point(&self) -> Vec3 {
+        match self.finish {
+            Some(finish) => finish.into(),
+            None => self.centerline().last().copied().unwrap_or_default(),
+        }
+    }
+    /// Banking in radians for every `centerline` point.
+    pub fn banking(&self) -> Vec<f32> {
+        let sign = match self.direction {
+            TrackDirection::Forward => 1.,
+            TrackDirection::Reverse => -1.,
+        };
+        self.resampled(
+            self.points
+                .iter()
+                .map(|p| sign * p.banking.to_radians())
+                .collect(),
+        )
+    }
+    pub fn widths(&self) -> Vec<f32> {
+        self.resampled(self.interpolate(|p| p.width, self.width))
+    }
+    pub fn runoffs_left(&self) -> Vec<f32> {
+        self.resampled(match self.direction {
+            TrackDirection::Forward => self.interpolate(|p| p.runoff_left, self.runoff),
+            TrackDirection::Reverse => self.interpolate(|p| p.runoff_right, self.runoff),
+        })
+    }
+    pub fn runoffs_right(&self) -> Vec<f32> {
+        self.resampled(match self.direction {
+            TrackDirection::Forward => self.interpolate(|p| p.runoff_right, self.runoff),
+            TrackDirection::Reverse => self.interpolate(|p| p.runoff_left, self.runoff),
+        })
+    }
+
+    /// Kerb override of the nearest control point for every `centerline` point.
+    pub fn kerb_overrides(&self) -> Vec<Option<KerbOverride>> {
+        let kerbs = self.ordered(self.points.iter().map(|p| p.kerb).collect::<Vec<_>>());
+        let n = kerbs.len();
+        self.samples()
+            .iter()
+            .map(|s| {
+                let kerb = kerbs[(s.span + (s.t >= 0.5) as usize) % n]?;
+                Some(match self.direction {
+                    TrackDirection::Forward => kerb,
+                    TrackDirection::Reverse => KerbOverride {
+                        left: kerb.right,
+                        right: kerb.left,
+                        ..kerb
+                    },
+                })
+            })
+            .collect()
+    }
+
+    fn ordered<T>(&self, mut values: Vec<T>) -> Vec<T> {
+        if self.direction == TrackDirection::Reverse {
+            values.reverse();
+        }
+        values
+    }
+
+    /// Per control point values interpolated onto the spline samples.
+    fn resampled(&self, values: Vec<f32>) -> Vec<f32> {
+        let values = self.ordered(values);
+        let n = values.len();
+        self.samples()
+            .iter()
+            .map(|s| {
+                let (a, b) = (values[s.span % n], values[(s.span + 1) % n]);
+                a + (b - a) * s.t
+            })
+            .collect()
+    }
+
+    /// Linear interpolation by distance between control points, wrapping around closed tracks.
+    fn interpolate(&self, value: impl Fn(&TrackPoint) -> Option<f32>, default: f32) -> Vec<f32> {
+        let n = self.points.len();
+        let mut distances: Vec<f32> = Vec::with_capacity(n);
+        let mut length = 0.;
+        for (i, p) in self.points.iter().enumerate() {
+            distances.push(length);
+            let next = &self.points[(i + 1) % n];
+            length += Vec3::from(p.position).distance(Vec3::from(next.position));
+        }
+        let controls: Vec<(usize, f32)> = self
+            .points
+            .iter()
+            .enumerate()
+            .filter_map(|(i, p)| value(p).map(|v| (i, v)))
+            .collect();
+        if controls.is_empty() {
+            return vec![default; n];
+        }
+        (0..n)
+            .map(|i| {
+                let next_k = controls.iter().position(|(ci, _)| *ci >= i);
+                let (next_i, next_v) = controls[next_k.unwrap_or(0)];
+                let (prev_i, prev_v) = match next_k {
+                    Some(k) if controls[k].0 == i => return controls[k].1,
+                    Some(0) if self.open => return next_v,
+                    None if self.open => return controls[controls.len() - 1].1,
+                    Some(0) | None => controls[controls.len() - 1],
+                    Some(k) => controls[k - 1],
+                };
+                let span = (distances[next_i] - distances[prev_i]).rem_euclid(length);
+                let along = (distances[i] - distances[prev_i]).rem_euclid(length);
+                if span > 0. {
+                    prev_v + (next_v - prev_v) * along / span
+                } else {
+                    prev_v
+                }
+            })
+            .collect()
+    }
+}
+
+#[derive(Resource)]
+pub struct TrackHandle(pub Handle<TrackAsset>);
+
+impl FromWorld for TrackHandle {
+    fn from_world(world: &mut World) -> Self {
+        let path = match world.get_resource::<TrackRegistry>() {
+            Some(registry) => registry.current().path.clone(),
+            None => DEFAULT_TRACK_PATH.to_string(),
+        };
+        let asset_server = world.resource::<AssetServer>();
+        Self(asset_server.load(path))
+    }
+}
+
+#[derive(Debug, Message)]
+pub struct TrackLoadedEvent;
+
+pub fn track_asset_event_system(
+    mut asset_events: MessageReader<AssetEvent<TrackAsset>>,
+    track_handle: Res<TrackHandle>,
+    mut loaded_events: MessageWriter<TrackLoadedEvent>,
+) {
+    for event in asset_events.read() {
+        if event.is_loaded_with_dependencies(&track_handle.0) {
+            loaded_events.write(TrackLoadedEvent);
+        }
+    }
+}
+
+#[derive(Default, TypePath)]
+pub struct TrackAssetLoader;
+
+#[non_exhaustive]
+#[derive(Debug, Error)]
+pub enum TrackAssetLoaderError {
+    #[error("Could not load track: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Could not parse track RON: {0}")]
+    RonSpannedError(#[from] ron::error::SpannedError),
+}
+
+impl AssetLoader for TrackAssetLoader {
+    type Asset = TrackAsset;
+    type Settings = ();
+    type Error = TrackAssetLoaderError;
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let options = ron::Options::default().with_default_extension(Extensions::IMPLICIT_SOME);
+        let track = options.from_bytes::<TrackAsset>(&bytes)?;
+        Ok(track)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["track.ron"]
+    }
+}
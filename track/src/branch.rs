@@ -0,0 +1,102 @@
+use crate::{
+    project_on_polyline, project_on_polyline_near, CarTrack, PolylineProjection, SpeedLimitZone,
+    TrackAsset, TrackBranch,
+};
+use bevy::prelude::*;
+use bevy_garage_car::Car;
+use bevy_rapier3d::na::Point3;
+use bevy_rapier3d::parry::shape::Polyline;
+use bevy_rapier3d::prelude::{Real, Velocity};
+
+/// Meters before the split and after the rejoin a car is still checked against a branch.
+const BRANCH_SPAN_MARGIN: f32 = 50.;
+
+#[derive(Debug, Clone)]
+pub struct TrackBranchConfig {
+    pub name: String,
+    pub polyline: Polyline,
+    pub segments: Vec<f32>,
+    pub length: f32,
+    /// Main polyline distances of the split and rejoin points.
+    pub split: f32,
+    pub rejoin: f32,
+    pub speed_limit: Option<SpeedLimitZone>,
+    pub pit_box: Option<Vec3>,
+}
+
+impl TrackBranchConfig {
+    pub fn new(
+        track_asset: &TrackAsset,
+        branch: &TrackBranch,
+        main_polyline: &Polyline,
+        main_segments: &[f32],
+    ) -> Self {
+        let centerline = track_asset.branch_asset(branch).centerline();
+        let vertices: Vec<Point3<Real>> = centerline
+            .iter()
+            .map(|p| Point3::new(p.x, p.y, p.z))
+            .collect();
+        let polyline = Polyline::new(vertices, None);
+        let mut segments: Vec<f32> = vec![];
+        let mut length = 0.;
+        for s in polyline.segments() {
+            segments.push(length);
+            length += s.length();
+        }
+        let main_distance = |p: Option<&Vec3>| {
+            p.map(|p| project_on_polyline(main_polyline, main_segments, *p).distance)
+                .unwrap_or(0.)
+        };
+        Self {
+            name: branch.name.clone(),
+            split: main_distance(centerline.first()),
+            rejoin: main_distance(centerline.last()),
+            polyline,
+            segments,
+            length,
+            speed_limit: branch.speed_limit,
+            pit_box: branch.pit_box.map(Vec3::from),
+        }
+    }
+
+    /// Main polyline distance matching a distance along the branch.
+    pub fn main_distance(&self, meters: f32, track_length: f32) -> f32 {
+        let span = (self.rejoin - self.split).rem_euclid(track_length);
+        let t = if self.length > 0. {
+            (meters / self.length).clamp(0., 1.)
+        } else {
+            0.
+        };
+        (self.split + span * t).rem_euclid(track_length)
+    }
+
+    /// Whether a main polyline distance is between the split and rejoin points, with a margin.
+    pub fn spans(&self, distance: f32, track_length: f32) -> bool {
+        let span = (self.rejoin - self.split).rem_euclid(track_length);
+        let from_split = (distance - self.split + BRANCH_SPAN_MARGIN).rem_euclid(track_length);
+        from_split <= span + 2. * BRANCH_SPAN_MARGIN
+    }
+
+    /// Projection onto the branch searching only the segments around a previous one.
+    pub fn project_near(&self, p: Vec3, hint: Option<usize>) -> PolylineProjection {
+        project_on_polyline_near(&self.polyline, &self.segments, false, p, hint)
+    }
+
+    pub fn speed_limit_at(&self, meters: f32) -> Option<f32> {
+        self.speed_limit
+            .filter(|zone| (zone.from..=zone.to).contains(&meters))
+            .map(|zone| zone.kmh)
+    }
+}
+
+/// Cuts the throttle of cars going faster than the speed limit zone they are in.
+pub fn speed_limit_system(mut cars: Query<(&CarTrack, &mut Car, &Velocity)>) {
+    for (car_track, mut car, velocity) in cars.iter_mut() {
+        let Some(kmh) = car_track.speed_limit else {
+            continue;
+        };
+        if velocity.linvel.length() * 3.6 > kmh {
+            car.gas = 0.;
+        }
+    }
+}
@@ -1,11 +1,31 @@
+use crate::{spawn_transform, LapTimer, Session, SpawnPosition, TrackConfig, TrackLimits};
 use bevy::prelude::*;
-use bevy_garage_car::spawn_car;
+use bevy_garage_car::{spawn_car, CarWheels};
+use bevy_rapier3d::prelude::Velocity;
 
 #[derive(Debug, Message)]
 pub struct SpawnCarOnTrackEvent {
+    /// The first car spawned is the player.
     pub player: bool,
+    /// Index of the first car, the others follow in order.
     pub index: usize,
-    pub position: Option<f32>,
+    pub count: usize,
+    /// Position of the first car, the others go to the next grid slots or behind it.
+    pub position: SpawnPosition,
+}
+
+/// Moves a car to a clear random spot on the track, keeping its laps.
+#[derive(Debug, Message)]
+pub struct RespawnCar {
+    pub car: Entity,
+}
+
+/// Car crossed the finish line of an open track.
+#[derive(Debug, Message)]
+pub struct StageFinished {
+    pub car: Entity,
+    pub index: usize,
+    pub time: f32,
 }
 
 #[derive(Component, Debug)]
@@ -17,7 +37,22 @@ pub struct CarTrack {
     pub lap: i32,
     pub line_dir: Vec3,
     pub line_pos: Vec3,
+    /// Racing line next to the car, the centerline on branches and until the line is solved.
+    pub racing_dir: Vec3,
+    pub racing_pos: Vec3,
+    /// Racing line target speed in m/s.
+    pub racing_speed: Option<f32>,
     pub place: usize,
+    pub stage_time: f32,
+    pub finished: bool,
+    /// Index into `TrackConfig::branches` when the car is off the main loop.
+    pub branch: Option<usize>,
+    /// Speed limit in km/h of the zone the car is in.
+    pub speed_limit: Option<f32>,
+    /// Main polyline segment the car was projected on last, where the next search starts.
+    pub segment: Option<usize>,
+    /// Segment of every branch the car was projected on last, by branch index.
+    pub branch_segments: Vec<Option<usize>>,
 }
 impl Default for CarTrack {
     fn default() -> Self {
@@ -30,6 +65,15 @@ impl Default for CarTrack {
             lap: 0,
             line_dir: Vec3::ZERO,
             line_pos: Vec3::ZERO,
+            racing_dir: Vec3::ZERO,
+            racing_pos: Vec3::ZERO,
+            racing_speed: None,
+            stage_time: 0.,
+            finished: false,
+            branch: None,
+            speed_limit: None,
+            segment: None,
+            branch_segments: vec![],
         }
     }
 }
@@ -44,10 +88,60 @@ pub fn spawn_car_on_track(
     start_shift: f32,
 ) -> Entity {
     let car_id = spawn_car(cmd, car_gl, wheel_gl, player, transform);
-    cmd.entity(car_id).insert(CarTrack {
-        index,
-        start_shift,
-        ..default()
-    });
+    cmd.entity(car_id).insert((
+        CarTrack {
+            index,
+            start_shift,
+            ..default()
+        },
+        LapTimer::new(start_shift.abs() < 1.),
+        TrackLimits::default(),
+    ));
     car_id
 }
+
+pub fn respawn_car_system(
+    mut events: MessageReader<RespawnCar>,
+    track_config: Res<TrackConfig>,
+    session: Res<Session>,
+    mut cars: Query<(Entity, &CarWheels, &mut CarTrack, &mut LapTimer)>,
+    mut bodies: Query<(&mut Transform, &mut Velocity)>,
+) {
+    if track_config.polyline.is_none() {
+        return;
+    }
+    for event in events.read() {
+        let occupied: Vec<Vec3> = cars
+            .iter()
+            .filter(|(e, ..)| *e != event.car)
+            .filter_map(|(e, ..)| bodies.get(e).ok().map(|(t, _)| t.translation))
+            .collect();
+        let Ok((e, wheels, mut car_track, mut lap_timer)) = cars.get_mut(event.car) else {
+            continue;
+        };
+        let Ok((from, _)) = bodies.get(e).map(|(t, v)| (*t, *v)) else {
+            continue;
+        };
+        let (to, meters) = spawn_transform(
+            &track_config,
+            &session.rules.grid,
+            SpawnPosition::Random,
+            &occupied,
+        );
+        // the wheels keep their place on the car
+        let rotation = to.rotation * from.rotation.inverse();
+        for entity in std::iter::once(e).chain(wheels.entities) {
+            if let Ok((mut transform, mut velocity)) = bodies.get_mut(entity) {
+                transform.translation =
+                    to.translation + rotation * (transform.translation - from.translation);
+                transform.rotation = rotation * transform.rotation;
+                *velocity = Velocity::zero();
+            }
+        }
+        car_track.start_shift = meters;
+        car_track.ride_distance = 0.;
+        car_track.segment = None;
+        car_track.branch_segments.clear();
+        lap_timer.respawned();
+    }
+}
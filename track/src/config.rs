@@ -1,26 +1,56 @@
+use crate::{Surface, SurfaceZone, TrackBranchConfig};
 use bevy::prelude::*;
-use bevy_rapier3d::parry::shape::Polyline;
+use bevy_rapier3d::na::Point3;
+use bevy_rapier3d::parry::query::PointQueryWithLocation;
+use bevy_rapier3d::parry::shape::{Polyline, SegmentPointLocation};
 use rand::Rng;
 // use std::f32::consts::PI;
 
+/// Segments searched on each side of the previous one when tracking a car.
+const PROJECTION_WINDOW: usize = 3;
+/// Cars further than this from the closest point in the window have jumped, after a respawn.
+const PROJECTION_MAX_OFFSET: f32 = 30.;
+
 #[derive(Resource)]
 pub struct TrackConfig {
     pub polyline: Option<Polyline>,
+    pub closed: bool,
+    pub normals: Vec<Vec3>,
+    /// Road edges for every polyline vertex.
+    pub left: Vec<Vec3>,
+    pub right: Vec<Vec3>,
+    pub tangents: Vec<Vec3>,
+    pub curvatures: Vec<f32>,
     pub segments: Vec<f32>,
     pub start_segment_i: usize,
     pub start_segment_shift: f32,
     pub start_shift: f32,
     pub track_length: f32,
+    pub finish_shift: f32,
+    /// Sector boundaries in meters from the start line.
+    pub sectors: Vec<f32>,
+    pub branches: Vec<TrackBranchConfig>,
+    pub surfaces: Vec<SurfaceZone>,
 }
 impl Default for TrackConfig {
     fn default() -> Self {
         Self {
             polyline: None,
+            closed: true,
+            normals: vec![],
+            left: vec![],
+            right: vec![],
+            tangents: vec![],
+            curvatures: vec![],
             segments: vec![],
             start_segment_i: 0,
             start_segment_shift: 0.,
             start_shift: 0.,
             track_length: 0.,
+            finish_shift: 0.,
+            sectors: vec![],
+            branches: vec![],
+            surfaces: vec![],
         }
     }
 }
@@ -33,32 +63,236 @@ impl TrackConfig {
     // }
     pub fn get_transform_random(&self) -> (Transform, f32) {
         let mut rng = rand::thread_rng();
-        let meters = rng.gen_range(0.0..self.track_length);
+        let meters = rng.gen_range(0.0..self.stage_length());
         let (translate, quat) = self.get_transform_by_meter(meters);
         let transform = Transform::from_translation(translate).with_rotation(quat);
         return (transform, meters);
     }
+    /// Projection onto the centerline searching only the segments around a previous one,
+    /// none without a polyline.
+    pub fn project_near(&self, p: Vec3, hint: Option<usize>) -> Option<PolylineProjection> {
+        let polyline = self.polyline.as_ref()?;
+        Some(project_on_polyline_near(
+            polyline,
+            &self.segments,
+            self.closed,
+            p,
+            hint,
+        ))
+    }
+    /// Distance from the start to the finish line, a full lap on closed tracks.
+    pub fn stage_length(&self) -> f32 {
+        match self.closed {
+            true => self.track_length,
+            false => self.finish_shift - self.start_shift,
+        }
+    }
     pub fn get_transform_by_meter(&self, meters: f32) -> (Vec3, Quat) {
-        let polyline = self.polyline.as_ref().unwrap();
-        let mut seg_meters = 0.;
-        let mut shift = meters + self.start_shift;
-        if shift > self.track_length {
-            shift = shift - self.track_length * (shift / self.track_length).floor();
+        let (i, t) = self.segment_at_meter(meters);
+        let dir = self.tangent_at_segment(i, t);
+        let up = self.normal_at(i, t).reject_from(dir).normalize_or(Vec3::Y);
+        let pos: Vec3 = self.position_at_segment(i, t) + up * 0.47;
+        let rotation = Quat::from_mat3(&Mat3::from_cols(up.cross(dir), up, dir));
+        (pos, rotation)
+    }
+    /// Segment index and parameter along it for a distance from the start line.
+    pub fn segment_at_meter(&self, meters: f32) -> (usize, f32) {
+        let shift = match self.closed {
+            true => (meters + self.start_shift).rem_euclid(self.track_length),
+            false => (meters + self.start_shift).clamp(0., self.track_length),
+        };
+        let i = self
+            .segments
+            .partition_point(|start| *start <= shift)
+            .saturating_sub(1);
+        let end = self
+            .segments
+            .get(i + 1)
+            .copied()
+            .unwrap_or(self.track_length);
+        let length = end - self.segments[i];
+        let t = if length > 0. {
+            (shift - self.segments[i]) / length
+        } else {
+            0.
+        };
+        (i, t.clamp(0., 1.))
+    }
+    pub fn position_at(&self, meters: f32) -> Vec3 {
+        let (i, t) = self.segment_at_meter(meters);
+        self.position_at_segment(i, t)
+    }
+    pub fn tangent_at(&self, meters: f32) -> Vec3 {
+        let (i, t) = self.segment_at_meter(meters);
+        self.tangent_at_segment(i, t)
+    }
+    /// Yaw in radians around Y, zero when heading towards +Z.
+    pub fn heading_at(&self, meters: f32) -> f32 {
+        let tangent = self.tangent_at(meters);
+        tangent.x.atan2(tangent.z)
+    }
+    /// Signed horizontal curvature in 1/m, positive when turning left.
+    pub fn curvature_at(&self, meters: f32) -> f32 {
+        let (i, t) = self.segment_at_meter(meters);
+        match (self.curvatures.get(i), self.curvatures.get(i + 1)) {
+            (Some(a), Some(b)) => a + (b - a) * t,
+            _ => 0.,
         }
+    }
+    fn position_at_segment(&self, segment_i: usize, t: f32) -> Vec3 {
+        let segment = self.polyline.as_ref().unwrap().segment(segment_i as u32);
+        Vec3::from(segment.a).lerp(Vec3::from(segment.b), t)
+    }
+    fn tangent_at_segment(&self, segment_i: usize, t: f32) -> Vec3 {
+        match (
+            self.tangents.get(segment_i),
+            self.tangents.get(segment_i + 1),
+        ) {
+            (Some(a), Some(b)) => a.lerp(*b, t).normalize_or(*a),
+            _ => {
+                let segment = self.polyline.as_ref().unwrap().segment(segment_i as u32);
+                segment.direction().map(Vec3::from).unwrap_or(Vec3::Z)
+            }
+        }
+    }
+    /// Left and right road edges at a parameter along a segment.
+    pub fn edges_at(&self, segment_i: usize, t: f32) -> Option<(Vec3, Vec3)> {
+        let left = self
+            .left
+            .get(segment_i)?
+            .lerp(*self.left.get(segment_i + 1)?, t);
+        let right = self
+            .right
+            .get(segment_i)?
+            .lerp(*self.right.get(segment_i + 1)?, t);
+        Some((left, right))
+    }
+    /// Lateral position of a point in meters from the right edge and the road width there.
+    pub fn lateral_at(&self, projection: &PolylineProjection, p: Vec3) -> Option<(f32, f32)> {
+        let (left, right) = self.edges_at(projection.segment, projection.t)?;
+        let (left, right) = (left.xz(), right.xz());
+        let lateral = (p.xz() - right).dot((left - right).normalize_or_zero());
+        Some((lateral, left.distance(right)))
+    }
+    /// Whether a point is between the road edges, widened by a margin on both sides,
+    /// the search starts from a segment hint when there is one.
+    pub fn is_within_edges(&self, p: Vec3, margin: f32, hint: Option<usize>) -> bool {
+        let Some(projection) = self.project_near(p, hint) else {
+            return true;
+        };
+        let Some((lateral, width)) = self.lateral_at(&projection, p) else {
+            return true;
+        };
+        (-margin..=width + margin).contains(&lateral)
+    }
+    /// Run-off zone surface at a distance along the polyline.
+    pub fn zone_surface(&self, distance: f32) -> Option<Surface> {
+        let meters = match self.closed {
+            true => (distance - self.start_shift).rem_euclid(self.track_length),
+            false => distance - self.start_shift,
+        };
+        self.surfaces
+            .iter()
+            .find(|zone| (zone.from..=zone.to).contains(&meters))
+            .map(|zone| zone.surface)
+    }
+    pub fn normal_at(&self, segment_i: usize, t: f32) -> Vec3 {
+        match (self.normals.get(segment_i), self.normals.get(segment_i + 1)) {
+            (Some(a), Some(b)) => a.lerp(*b, t).normalize_or(Vec3::Y),
+            _ => Vec3::Y,
+        }
+    }
+}
 
-        for segment in polyline.segments() {
-            let new_seg_meters: f32 = seg_meters + segment.length();
-            if new_seg_meters < shift {
-                seg_meters = new_seg_meters;
-            } else {
-                let a: Vec3 = segment.a.into();
-                let dir: Vec3 = segment.direction().unwrap().into();
-                let mut pos: Vec3 = a + dir * (shift - seg_meters);
-                pos.y = 0.47;
+pub struct PolylineProjection {
+    /// Distance along the polyline.
+    pub distance: f32,
+    /// Distance from the point to the polyline.
+    pub offset: f32,
+    pub position: Vec3,
+    pub direction: Vec3,
+    pub segment: usize,
+    /// Parameter along the segment.
+    pub t: f32,
+}
+
+pub fn project_on_polyline(
+    polyline: &Polyline,
+    segments: &[f32],
+    point: Vec3,
+) -> PolylineProjection {
+    let (projection, (segment_i, location)) =
+        polyline.project_local_point_and_get_location(&Point3::from(point), true);
+    let segment = polyline.segment(segment_i);
+    let along = match location {
+        SegmentPointLocation::OnVertex(1) => segment.length(),
+        SegmentPointLocation::OnVertex(_) => 0.,
+        SegmentPointLocation::OnEdge(uv) => uv[1] * segment.length(),
+    };
+    let position = Vec3::from(projection.point);
+    let length = segment.length();
+    PolylineProjection {
+        distance: segments[segment_i as usize] + along,
+        offset: point.distance(position),
+        position,
+        direction: segment.direction().map(Vec3::from).unwrap_or(Vec3::Z),
+        segment: segment_i as usize,
+        t: if length > 0. { along / length } else { 0. },
+    }
+}
 
-                return (pos, Quat::from_rotation_arc(Vec3::Z, dir));
+/// Projection searching only the segments around a previous one,
+/// the whole polyline when there is none or the closest point leaves the window.
+pub fn project_on_polyline_near(
+    polyline: &Polyline,
+    segments: &[f32],
+    closed: bool,
+    p: Vec3,
+    hint: Option<usize>,
+) -> PolylineProjection {
+    let n = polyline.num_segments();
+    let Some(hint) = hint.filter(|hint| *hint < n && n > 2 * PROJECTION_WINDOW) else {
+        return project_on_polyline(polyline, segments, p);
+    };
+    let vertices = polyline.vertices();
+    let vertex = |i: usize| Vec3::new(vertices[i].x, vertices[i].y, vertices[i].z);
+    // closest segment by squared distance, the projection is built for that one only
+    let mut best: Option<(usize, usize, f32, f32)> = None;
+    for k in 0..=2 * PROJECTION_WINDOW {
+        let i = hint as isize + k as isize - PROJECTION_WINDOW as isize;
+        let i = match closed {
+            true => i.rem_euclid(n as isize) as usize,
+            false if (0..n as isize).contains(&i) => i as usize,
+            false => continue,
+        };
+        let (a, b) = (vertex(i), vertex(i + 1));
+        let ab = b - a;
+        let length_sq = ab.length_squared();
+        let t = match length_sq > 0. {
+            true => ((p - a).dot(ab) / length_sq).clamp(0., 1.),
+            false => 0.,
+        };
+        let distance_sq = p.distance_squared(a + ab * t);
+        if best.is_none_or(|(_, _, _, best)| distance_sq < best) {
+            best = Some((k, i, t, distance_sq));
+        }
+    }
+    match best {
+        // the closest point may be further along when it sits on the edge of the window
+        Some((k, i, t, distance_sq))
+            if distance_sq < PROJECTION_MAX_OFFSET * PROJECTION_MAX_OFFSET
+                && ((k > 0 && k < 2 * PROJECTION_WINDOW) || !closed && (i == 0 || i + 1 == n)) =>
+        {
+            let (a, b) = (vertex(i), vertex(i + 1));
+            PolylineProjection {
+                distance: segments[i] + a.distance(b) * t,
+                offset: distance_sq.sqrt(),
+                position: a.lerp(b, t),
+                direction: (b - a).normalize_or(Vec3::Z),
+                segment: i,
+                t,
             }
         }
-        panic!();
+        _ => project_on_polyline(polyline, segments, p),
     }
 }
@@ -1,11 +1,21 @@
-use crate::TrackConfig;
+use crate::{scenery_placements, spawn_scenery, TrackAsset, TrackConfig, TrackEntity, TrackHandle};
 use bevy::prelude::*;
 use std::f32::consts::PI;
 
+/// Light that is on at night only.
+#[derive(Component, Debug)]
+pub struct NightLight {
+    pub intensity: f32,
+}
+
 pub fn track_decorations_start_system(
     asset_server: Res<AssetServer>,
     mut cmd: Commands,
     track_config: Res<TrackConfig>,
+    track_handle: Res<TrackHandle>,
+    tracks: Res<Assets<TrackAsset>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
 ) {
     let gl_object = asset_server.load("overheadLights.glb#Scene0");
     let (translate, quat) = track_config.get_transform_by_meter(0.);
@@ -16,5 +26,37 @@ pub fn track_decorations_start_system(
         Transform::from_scale(Vec3::ONE * 15.)
             .with_translation(translate)
             .with_rotation(quat),
+        TrackEntity,
     ));
+
+    // lamps under the gantry light up the start line at night
+    let (start, start_quat) = track_config.get_transform_by_meter(0.);
+    for x in [-3., 0., 3.] {
+        cmd.spawn((
+            SpotLight {
+                color: Color::srgb(1., 0.95, 0.85),
+                intensity: 0.,
+                range: 30.,
+                outer_angle: 0.9,
+                inner_angle: 0.6,
+                shadows_enabled: false,
+                ..default()
+            },
+            Transform::from_translation(start + start_quat.mul_vec3(Vec3::new(x, 7., 0.)))
+                .looking_to(-Vec3::Y, start_quat.mul_vec3(Vec3::Z)),
+            NightLight {
+                intensity: 4_000_000.,
+            },
+            TrackEntity,
+        ));
+    }
+
+    if let Some(track_asset) = tracks.get(&track_handle.0) {
+        spawn_scenery(
+            &mut cmd,
+            &mut meshes,
+            &mut materials,
+            &scenery_placements(track_asset, &track_config),
+        );
+    }
 }
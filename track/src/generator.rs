@@ -0,0 +1,219 @@
+use crate::{validate_track, TrackAsset, TrackDirection, TrackIssue, TrackPoint};
+use bevy::prelude::*;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::f32::consts::{FRAC_PI_8, TAU};
+use thiserror::Error;
+
+/// Corner radius may undershoot the generator minimum by this factor after spline fitting.
+const LAYOUT_RADIUS_TOLERANCE: f32 = 0.8;
+
+/// Random closed circuit made of straights and constant radius corners.
+#[derive(Debug, Clone)]
+pub struct TrackGenerator {
+    pub seed: u64,
+    /// Target centerline length in meters.
+    pub length: f32,
+    pub corners: usize,
+    /// Corner radius range in meters.
+    pub min_radius: f32,
+    pub max_radius: f32,
+    /// Straight length range in meters.
+    pub min_straight: f32,
+    pub max_straight: f32,
+    pub width: f32,
+    pub runoff: f32,
+    /// Layouts tried before giving up.
+    pub attempts: usize,
+}
+
+impl Default for TrackGenerator {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            length: 2000.,
+            corners: 8,
+            min_radius: 20.,
+            max_radius: 120.,
+            min_straight: 20.,
+            max_straight: 500.,
+            width: 5.,
+            runoff: 2.5,
+            attempts: 200,
+        }
+    }
+}
+
+#[non_exhaustive]
+#[derive(Debug, Error)]
+pub enum TrackLayoutError {
+    #[error("corner radius {radius:.1}m is below {min:.1}m")]
+    CornerTooTight { radius: f32, min: f32 },
+    #[error("{0}")]
+    Geometry(TrackIssue),
+    #[error("straight of {0:.1}m is out of range")]
+    StraightLength(f32),
+    #[error("no valid layout found in {0} attempts")]
+    NoValidLayout(usize),
+}
+
+struct Corner {
+    apex: Vec2,
+    radius: f32,
+    /// Distance from the apex to where the arc meets the straights.
+    tangent: f32,
+    turn: f32,
+}
+
+impl TrackGenerator {
+    pub fn new(seed: u64) -> Self {
+        Self { seed, ..default() }
+    }
+
+    pub fn name(&self) -> String {
+        format!("generated-{}", self.seed)
+    }
+
+    /// Same seed and parameters always give the same track.
+    pub fn generate(&self) -> Result<TrackAsset, TrackLayoutError> {
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        for _ in 0..self.attempts {
+            if let Ok(asset) = self.layout(&mut rng).and_then(|asset| self.check(asset)) {
+                return Ok(asset);
+            }
+        }
+        Err(TrackLayoutError::NoValidLayout(self.attempts))
+    }
+
+    fn check(&self, asset: TrackAsset) -> Result<TrackAsset, TrackLayoutError> {
+        if let Some(issue) = validate_track(&asset).into_iter().next() {
+            return Err(TrackLayoutError::Geometry(issue));
+        }
+        let min = self.min_radius * LAYOUT_RADIUS_TOLERANCE;
+        let radius = asset
+            .samples()
+            .iter()
+            .map(|s| 1. / s.curvature.abs())
+            .fold(f32::MAX, f32::min);
+        match radius < min {
+            true => Err(TrackLayoutError::CornerTooTight { radius, min }),
+            false => Ok(asset),
+        }
+    }
+
+    fn layout(&self, rng: &mut StdRng) -> Result<TrackAsset, TrackLayoutError> {
+        let n = self.corners.max(3);
+        let step = TAU / n as f32;
+        let apexes: Vec<Vec2> = (0..n)
+            .map(|i| {
+                let angle = step * (i as f32 + rng.gen_range(-0.35..0.35));
+                Vec2::from_angle(angle) * rng.gen_range(0.5..1.)
+            })
+            .collect();
+        let radii: Vec<f32> = (0..n)
+            .map(|_| rng.gen_range(self.min_radius..=self.max_radius))
+            .collect();
+
+        // scale the polygon so the filleted loop gets the target length
+        let edge = |i: usize| apexes[(i + 1) % n] - apexes[i];
+        let perimeter: f32 = (0..n).map(|i| edge(i).length()).sum();
+        let turns: Vec<f32> = (0..n)
+            .map(|i| edge((i + n - 1) % n).angle_to(edge(i)))
+            .collect();
+        let shortening: f32 = (0..n)
+            .map(|i| {
+                let turn = turns[i].abs();
+                2. * radii[i] * (turn / 2.).tan() - radii[i] * turn
+            })
+            .sum();
+        let scale = (self.length + shortening) / perimeter;
+        let corners: Vec<Corner> = (0..n)
+            .map(|i| Corner {
+                apex: apexes[i] * scale,
+                radius: radii[i],
+                tangent: radii[i] * (turns[i].abs() / 2.).tan(),
+                turn: turns[i],
+            })
+            .collect();
+
+        let mut points: Vec<Vec2> = vec![];
+        let mut start = Vec2::ZERO;
+        let mut longest = 0.;
+        for (i, corner) in corners.iter().enumerate() {
+            let next = &corners[(i + 1) % n];
+            let straight = corner.apex.distance(next.apex) - corner.tangent - next.tangent;
+            if !(self.min_straight..=self.max_straight).contains(&straight) {
+                return Err(TrackLayoutError::StraightLength(straight));
+            }
+            let prev = &corners[(i + n - 1) % n];
+            let dir_in = (corner.apex - prev.apex).normalize();
+            let dir_out = (next.apex - corner.apex).normalize();
+            let entry = corner.apex - dir_in * corner.tangent;
+            let normal = match corner.turn > 0. {
+                true => dir_in.perp(),
+                false => -dir_in.perp(),
+            };
+            let center = entry + normal * corner.radius;
+            let arc_steps = (corner.turn.abs() / FRAC_PI_8).ceil().max(1.) as usize;
+            for j in 0..=arc_steps {
+                let angle = corner.turn * j as f32 / arc_steps as f32;
+                points.push(center + Vec2::from_angle(angle).rotate(entry - center));
+            }
+            let exit = corner.apex + dir_out * corner.tangent;
+            if straight > longest {
+                longest = straight;
+                start = exit + dir_out * straight / 2.;
+            }
+        }
+
+        Ok(TrackAsset {
+            name: self.name(),
+            width: self.width,
+            runoff: self.runoff,
+            start: [start.x, 0., start.y],
+            open: false,
+            finish: None,
+            sectors: vec![],
+            direction: TrackDirection::Forward,
+            points: points
+                .iter()
+                .map(|p| TrackPoint::new([p.x, 0., p.y]))
+                .collect(),
+            branches: vec![],
+            surfaces: vec![],
+            kerbs: default(),
+            scenery: vec![],
+            scatter: vec![],
+            barriers: vec![],
+            session: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn positions(asset: &TrackAsset) -> Vec<[f32; 3]> {
+        asset.points.iter().map(|p| p.position).collect()
+    }
+
+    #[test]
+    fn same_seed_same_track() {
+        let a = TrackGenerator::new(7).generate().unwrap();
+        let b = TrackGenerator::new(7).generate().unwrap();
+        assert_eq!(positions(&a), positions(&b));
+        assert_eq!(a.start, b.start);
+        let c = TrackGenerator::new(8).generate().unwrap();
+        assert_ne!(positions(&a), positions(&c));
+    }
+
+    #[test]
+    fn generated_tracks_are_valid() {
+        for seed in 0..5 {
+            let asset = TrackGenerator::new(seed).generate().unwrap();
+            assert_eq!(validate_track(&asset), vec![], "seed {seed}");
+            assert!(!asset.open);
+        }
+    }
+}
@@ -0,0 +1,304 @@
+use crate::TrackConfig;
+use bevy::prelude::*;
+use bevy_garage_car::{CarWheels, Wheel};
+use bevy_rapier3d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Meters kept along the track between the centers of spawned cars.
+const SPAWN_CLEARANCE: f32 = 6.;
+/// Meters across the track between the centers of cars side by side.
+const SPAWN_WIDTH: f32 = 3.;
+/// Random spots tried before a car is moved back from the last one.
+const SPAWN_RANDOM_TRIES: usize = 20;
+/// Steps of `SPAWN_CLEARANCE` a car is moved back, or ahead, at most to find a clear spot.
+const SPAWN_MAX_STEPS: usize = 100;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PoleSide {
+    #[default]
+    Left,
+    Right,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StartKind {
+    /// Staggered grid behind the line, started by the lights.
+    #[default]
+    Standing,
+    /// Two by two formation further back, moving, started when it reaches the line.
+    Rolling,
+}
+
+/// Where the cars line up for the race start.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GridLayout {
+    pub start: StartKind,
+    pub pole_side: PoleSide,
+    /// Meters from the start line back to the pole position car.
+    pub pole_distance: f32,
+    /// Meters between rows of two cars, the second car of a standing row is half a row back.
+    pub row_spacing: f32,
+    /// Meters from the centerline to the cars of each column.
+    pub column_offset: f32,
+    /// Meters from the start line back to the front row of a rolling start.
+    pub rolling_distance: f32,
+    /// Formation speed of a rolling start in km/h.
+    pub rolling_speed: f32,
+}
+
+impl Default for GridLayout {
+    fn default() -> Self {
+        Self {
+            start: StartKind::Standing,
+            pole_side: PoleSide::Left,
+            pole_distance: 5.,
+            row_spacing: 16.,
+            column_offset: 2.,
+            rolling_distance: 150.,
+            rolling_speed: 80.,
+        }
+    }
+}
+
+impl GridLayout {
+    /// Meters from the start line and offset to the left of the centerline of a slot from pole.
+    pub fn slot(&self, slot: usize) -> (f32, f32) {
+        let (row, column) = ((slot / 2) as f32, slot % 2);
+        let left = match (column, self.pole_side) {
+            (0, PoleSide::Left) | (1, PoleSide::Right) => 1.,
+            _ => -1.,
+        };
+        let meters = match self.start {
+            StartKind::Standing => {
+                -self.pole_distance - (row + column as f32 / 2.) * self.row_spacing
+            }
+            StartKind::Rolling => -self.rolling_distance - row * self.row_spacing,
+        };
+        (meters, left * self.column_offset)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum SpawnPosition {
+    /// Meters from the start line.
+    Meters(f32),
+    Random,
+    /// Grid slot counted from pole position.
+    Grid(usize),
+}
+
+/// Whether a car at the transform keeps clear of the occupied positions, cars of a row are
+/// closer across the track than a car length.
+fn is_clear(transform: &Transform, occupied: &[Vec3]) -> bool {
+    occupied.iter().all(|p| {
+        let to = *p - transform.translation;
+        to.dot(transform.rotation * Vec3::Z).abs() >= SPAWN_CLEARANCE
+            || to.dot(transform.rotation * Vec3::X).abs() >= SPAWN_WIDTH
+    })
+}
+
+/// Spawn transform and meters from the start line, moved back along the track
+/// until the car is clear of the occupied positions, beside or ahead of them at the start
+/// of an open track.
+pub fn spawn_transform(
+    track_config: &TrackConfig,
+    layout: &GridLayout,
+    position: SpawnPosition,
+    occupied: &[Vec3],
+) -> (Transform, f32) {
+    let transform_at = |meters: f32, left: f32| {
+        let (translation, rotation) = track_config.get_transform_by_meter(meters);
+        Transform::from_translation(translation + rotation * Vec3::X * left).with_rotation(rotation)
+    };
+    let clear = |transform: &Transform| is_clear(transform, occupied);
+    let (meters, left) = match position {
+        SpawnPosition::Meters(meters) => (meters, 0.),
+        SpawnPosition::Grid(slot) => layout.slot(slot),
+        SpawnPosition::Random => {
+            let mut meters = 0.;
+            for _ in 0..SPAWN_RANDOM_TRIES {
+                let (transform, random) = track_config.get_transform_random();
+                meters = random;
+                if clear(&transform) {
+                    break;
+                }
+            }
+            (meters, 0.)
+        }
+    };
+    // open tracks end behind the start, no car goes further back than the first meter
+    let lowest = match track_config.closed {
+        true => f32::MIN,
+        false => -track_config.start_shift,
+    };
+    let meters = meters.max(lowest);
+    let beside = match left == 0. {
+        true => layout.column_offset,
+        false => -left,
+    };
+    let behind = (0..SPAWN_MAX_STEPS)
+        .map(|step| meters - step as f32 * SPAWN_CLEARANCE)
+        .take_while(|meters| *meters >= lowest);
+    // out of road behind, the car goes to the other side and then ahead
+    let ahead = (1..SPAWN_MAX_STEPS)
+        .map(|step| meters + step as f32 * SPAWN_CLEARANCE)
+        .take_while(|meters| *meters <= track_config.stage_length())
+        .flat_map(|meters| [(meters, left), (meters, beside)]);
+    let spot = behind
+        .clone()
+        .map(|meters| (meters, left))
+        .chain(behind.map(|meters| (meters, beside)))
+        .chain(ahead)
+        .map(|(meters, left)| (transform_at(meters, left), meters))
+        .find(|(transform, _)| clear(transform));
+    let (transform, mut meters) = match spot {
+        Some(spot) => spot,
+        None => {
+            warn!("no clear spot to spawn a car at {meters:.1}m");
+            (transform_at(meters, left), meters)
+        }
+    };
+    if track_config.closed {
+        meters = meters.rem_euclid(track_config.track_length);
+    }
+    (transform, meters)
+}
+
+/// Car of a rolling start, set moving at the formation speed in m/s once spawned.
+#[derive(Component, Debug)]
+pub struct RollingStart(pub f32);
+
+pub fn rolling_start_system(
+    mut cmd: Commands,
+    cars: Query<(Entity, &Transform, &CarWheels, &RollingStart)>,
+    mut velocities: Query<(&mut Velocity, Option<&Wheel>)>,
+) {
+    for (e, transform, wheels, rolling_start) in cars.iter() {
+        let linvel = transform.rotation * Vec3::Z * rolling_start.0;
+        let axle = transform.rotation * Vec3::X;
+        for entity in std::iter::once(e).chain(wheels.entities) {
+            if let Ok((mut velocity, wheel)) = velocities.get_mut(entity) {
+                velocity.linvel = linvel;
+                // wheels roll without slipping
+                velocity.angvel = match wheel {
+                    Some(wheel) => axle * rolling_start.0 / wheel.radius,
+                    None => Vec3::ZERO,
+                };
+            }
+        }
+        cmd.entity(e).remove::<RollingStart>();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy_rapier3d::na::Point3;
+    use bevy_rapier3d::parry::shape::Polyline;
+
+    /// Road along +Z, a point every 10m, starting `start` meters in.
+    fn straight(length: f32, start: f32, closed: bool) -> TrackConfig {
+        let points: Vec<Vec3> = (0..=(length / 10.) as usize)
+            .map(|i| Vec3::Z * i as f32 * 10.)
+            .collect();
+        TrackConfig {
+            polyline: Some(Polyline::new(
+                points.iter().map(|p| Point3::new(p.x, p.y, p.z)).collect(),
+                None,
+            )),
+            closed,
+            normals: vec![Vec3::Y; points.len()],
+            tangents: vec![Vec3::Z; points.len()],
+            segments: (0..points.len() - 1).map(|i| i as f32 * 10.).collect(),
+            start_shift: start,
+            track_length: length,
+            finish_shift: length,
+            ..default()
+        }
+    }
+
+    #[test]
+    fn standing_grid_is_staggered() {
+        let layout = GridLayout::default();
+        assert_eq!(layout.slot(0), (-5., 2.));
+        assert_eq!(layout.slot(1), (-13., -2.));
+        assert_eq!(layout.slot(2), (-21., 2.));
+        assert_eq!(layout.slot(3), (-29., -2.));
+    }
+
+    #[test]
+    fn pole_side_picks_the_first_column() {
+        let layout = GridLayout {
+            pole_side: PoleSide::Right,
+            ..default()
+        };
+        assert_eq!(layout.slot(0), (-5., -2.));
+        assert_eq!(layout.slot(1), (-13., 2.));
+    }
+
+    #[test]
+    fn rolling_grid_goes_two_by_two() {
+        let layout = GridLayout {
+            start: StartKind::Rolling,
+            row_spacing: 10.,
+            ..default()
+        };
+        assert_eq!(layout.slot(0), (-150., 2.));
+        assert_eq!(layout.slot(1), (-150., -2.));
+        assert_eq!(layout.slot(2), (-160., 2.));
+    }
+
+    #[test]
+    fn spawn_moves_back_from_occupied_spots() {
+        let track_config = straight(500., 250., true);
+        let layout = GridLayout::default();
+        let (first, meters) =
+            spawn_transform(&track_config, &layout, SpawnPosition::Meters(0.), &[]);
+        assert_eq!(meters, 0.);
+        let (second, meters) = spawn_transform(
+            &track_config,
+            &layout,
+            SpawnPosition::Meters(0.),
+            &[first.translation],
+        );
+        assert_eq!(meters, 500. - SPAWN_CLEARANCE);
+        assert!(second.translation.distance(first.translation) >= SPAWN_CLEARANCE);
+    }
+
+    #[test]
+    fn open_track_grid_stays_on_the_road() {
+        // the grid runs out of road behind the start after a few rows
+        let track_config = straight(500., 32., false);
+        let layout = GridLayout::default();
+        let mut occupied: Vec<Vec3> = vec![];
+        for slot in 0..10 {
+            let (transform, meters) =
+                spawn_transform(&track_config, &layout, SpawnPosition::Grid(slot), &occupied);
+            assert!(meters >= -32., "slot {slot} at {meters}m");
+            assert!(is_clear(&transform, &occupied), "slot {slot} at {meters}m");
+            occupied.push(transform.translation);
+        }
+    }
+
+    #[test]
+    fn rolling_grid_spawns_two_by_two() {
+        let track_config = straight(500., 250., true);
+        let layout = GridLayout {
+            start: StartKind::Rolling,
+            ..default()
+        };
+        let mut occupied: Vec<Vec3> = vec![];
+        let mut rows: Vec<f32> = vec![];
+        for slot in 0..6 {
+            let (transform, meters) =
+                spawn_transform(&track_config, &layout, SpawnPosition::Grid(slot), &occupied);
+            occupied.push(transform.translation);
+            rows.push(meters);
+        }
+        for (row, cars) in rows.chunks(2).enumerate() {
+            assert_eq!(cars[0], cars[1], "row {row}");
+            assert_eq!(cars[0], 500. - 150. - row as f32 * layout.row_spacing);
+        }
+    }
+}
@@ -1,14 +1,153 @@
-use super::MaterialHandle;
+use super::{CellLod, CullCell, MaterialHandle, Surface, Track, TrackEntity};
 use crate::mesh::QuadPlane;
+use bevy::asset::RenderAssetUsages;
 use bevy::camera::primitives::Aabb;
 use bevy::light::NotShadowCaster;
+use bevy::mesh::{Indices, PrimitiveTopology};
 use bevy::prelude::*;
 use bevy_garage_car::STATIC_GROUP;
 use bevy_rapier3d::prelude::*;
 
 #[derive(Component, Debug)]
-pub struct GroundCell {
-    pub is_color: bool,
+pub struct GroundCell;
+
+const GROUND_CELL_SIZE: f32 = 5.;
+const GROUND_CELL_SUBDIVISIONS: usize = 10;
+const GROUND_CELL_FAR_SUBDIVISIONS: usize = 2;
+const GROUND_FALLOFF: f32 = 60.;
+const GROUND_DROP: f32 = 0.02;
+
+struct GroundSegment {
+    a: Vec3,
+    b: Vec3,
+    left_a: Vec3,
+    left_b: Vec3,
+    edge_left: (f32, f32),
+    edge_right: (f32, f32),
+}
+
+/// Ground height field that follows the road surface out to the walls
+/// and blends into the surrounding track elevation further away.
+pub struct GroundTerrain {
+    segments: Vec<GroundSegment>,
+    flat: Option<f32>,
+}
+
+impl GroundTerrain {
+    pub fn new(tracks: &[&Track]) -> Self {
+        let y0 = tracks
+            .first()
+            .and_then(|track| track.points.first())
+            .map(|p| p.y)
+            .unwrap_or(0.);
+        let is_flat = tracks.iter().all(|track| {
+            track.points.iter().all(|p| (p.y - y0).abs() < 0.001)
+                && track.normals.iter().all(|n| n.abs_diff_eq(Vec3::Y, 0.001))
+        });
+        let segments = tracks
+            .iter()
+            .flat_map(|track| {
+                (0..track.points.len().saturating_sub(1)).map(|i| GroundSegment {
+                    a: track.points[i],
+                    b: track.points[i + 1],
+                    left_a: track.left_norm[i],
+                    left_b: track.left_norm[i + 1],
+                    edge_left: (track.wall_offset_left(i), track.wall_offset_left(i + 1)),
+                    edge_right: (track.wall_offset_right(i), track.wall_offset_right(i + 1)),
+                })
+            })
+            .collect();
+        Self {
+            segments,
+            flat: is_flat.then_some(y0 - 0.001),
+        }
+    }
+
+    pub fn is_flat(&self) -> bool {
+        self.flat.is_some()
+    }
+
+    pub fn height(&self, p: Vec2) -> f32 {
+        if let Some(y) = self.flat {
+            return y;
+        }
+        let mut nearest_d = f32::MAX;
+        let mut nearest_h = 0.;
+        let mut nearest_edge = 0.;
+        let (mut far_sum, mut far_w) = (0., 0.);
+        for s in self.segments.iter() {
+            let (a, b) = (s.a.xz(), s.b.xz());
+            let ab = b - a;
+            let len_sq = ab.length_squared();
+            let t = if len_sq > 0. {
+                ((p - a).dot(ab) / len_sq).clamp(0., 1.)
+            } else {
+                0.
+            };
+            let closest = a + ab * t;
+            let d = p.distance(closest);
+            let y = s.a.y + (s.b.y - s.a.y) * t;
+
+            let w = 1. / (d * d + 1.);
+            far_sum += y * w;
+            far_w += w;
+
+            if d < nearest_d {
+                let left = s.left_a.lerp(s.left_b, t);
+                let left_flat = left.xz();
+                let bank = if left_flat.length() > 0. {
+                    left.y / left_flat.length()
+                } else {
+                    0.
+                };
+                let lateral = (p - closest).dot(left_flat.normalize_or_zero());
+                let edge_left = s.edge_left.0 + (s.edge_left.1 - s.edge_left.0) * t;
+                let edge_right = s.edge_right.0 + (s.edge_right.1 - s.edge_right.0) * t;
+                nearest_d = d;
+                nearest_h = y + lateral.clamp(-edge_right, edge_left) * bank;
+                nearest_edge = if lateral > 0. { edge_left } else { edge_right };
+            }
+        }
+        let far_h = if far_w > 0. { far_sum / far_w } else { 0. };
+        let x = ((nearest_d - nearest_edge) / GROUND_FALLOFF).clamp(0., 1.);
+        let blend = x * x * (3. - 2. * x);
+        nearest_h + (far_h - nearest_h) * blend - GROUND_DROP
+    }
+
+    pub fn cell_mesh(&self, center: Vec2, size: Vec2, subdivisions: usize) -> Mesh {
+        let n = subdivisions + 1;
+        let mut positions: Vec<[f32; 3]> = Vec::with_capacity(n * n);
+        let mut uvs: Vec<[f32; 2]> = Vec::with_capacity(n * n);
+        for iz in 0..n {
+            for ix in 0..n {
+                let u = ix as f32 / subdivisions as f32;
+                let v = iz as f32 / subdivisions as f32;
+                let local = Vec2::new((u - 0.5) * size.x, (v - 0.5) * size.y);
+                let h = self.height(center + local);
+                positions.push([local.x, h, local.y]);
+                uvs.push([u, 1. - v]);
+            }
+        }
+        let mut indices: Vec<u32> = Vec::with_capacity(subdivisions * subdivisions * 6);
+        for iz in 0..subdivisions {
+            for ix in 0..subdivisions {
+                let a = (iz * n + ix) as u32;
+                let b = a + 1;
+                let c = a + n as u32;
+                let d = c + 1;
+                indices.extend([a, c, b, b, c, d]);
+            }
+        }
+        let mut mesh = Mesh::new(
+            PrimitiveTopology::TriangleList,
+            RenderAssetUsages::RENDER_WORLD,
+        );
+        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+        mesh.insert_indices(Indices::U32(indices));
+        mesh.compute_smooth_normals();
+        mesh
+    }
 }
 
 pub fn spawn_ground_heightfield(
@@ -16,51 +155,89 @@ pub fn spawn_ground_heightfield(
     meshes: &mut ResMut<Assets<Mesh>>,
     handled_materials: &Res<MaterialHandle>,
     aabb: &Aabb,
+    tracks: &[&Track],
     padding: f32,
 ) {
     let aabb_center: Vec3 = aabb.center.into();
     let size: Vec2 = 2. * (aabb.half_extents.xz() + padding * Vec2::ONE);
-    let (cols, rows) = (10, 10);
+    let terrain = GroundTerrain::new(tracks);
 
     let meshes_n_half = 10;
     let size_s = size / (2 * meshes_n_half) as f32;
-    let mut mesh = Mesh::from(QuadPlane::new(size_s));
-    mesh.generate_tangents().unwrap();
-
-    let mesh_handle = meshes.add(mesh.clone());
+    let flat_mesh_handle = terrain.is_flat().then(|| {
+        let mut mesh = Mesh::from(QuadPlane::new(size_s));
+        mesh.generate_tangents().unwrap();
+        meshes.add(mesh)
+    });
     for x in -meshes_n_half..meshes_n_half {
         for z in -meshes_n_half..meshes_n_half {
+            let center = Vec2::new(
+                aabb_center.x + x as f32 * size_s.x + size_s.x / 2.,
+                aabb_center.z + z as f32 * size_s.y + size_s.y / 2.,
+            );
+            let (lod, y) = match &flat_mesh_handle {
+                Some(handle) => (
+                    CellLod {
+                        near: handle.clone(),
+                        far: handle.clone(),
+                    },
+                    terrain.height(center),
+                ),
+                None => {
+                    let mut cell_mesh = |subdivisions: usize| {
+                        let mut mesh = terrain.cell_mesh(center, size_s, subdivisions);
+                        mesh.generate_tangents().unwrap();
+                        meshes.add(mesh)
+                    };
+                    (
+                        CellLod {
+                            near: cell_mesh(GROUND_CELL_SUBDIVISIONS),
+                            far: cell_mesh(GROUND_CELL_FAR_SUBDIVISIONS),
+                        },
+                        0.,
+                    )
+                }
+            };
             cmd.spawn((
-                Mesh3d(mesh_handle.clone()),
+                Mesh3d(lod.near.clone()),
                 MeshMaterial3d(handled_materials.ground.clone()),
-                Transform::from_translation(Vec3::new(
-                    aabb_center.x + x as f32 * size_s.x + size_s.x / 2.,
-                    0.,
-                    aabb_center.z + z as f32 * size_s.y + size_s.y / 2.,
-                )),
+                Transform::from_translation(Vec3::new(center.x, y, center.y)),
                 NotShadowCaster,
-                GroundCell {
-                    // mesh_handle,
-                    is_color: false,
-                },
+                GroundCell,
+                CullCell::default(),
+                lod,
+                TrackEntity,
             ));
         }
     }
 
+    let (cols, rows) = match terrain.is_flat() {
+        true => (10, 10),
+        false => (
+            (size.x / GROUND_CELL_SIZE) as usize + 1,
+            (size.y / GROUND_CELL_SIZE) as usize + 1,
+        ),
+    };
+    let mut heights: Vec<f32> = Vec::with_capacity(rows * cols);
+    for col in 0..cols {
+        for row in 0..rows {
+            let x = aabb_center.x + (col as f32 / (cols - 1) as f32 - 0.5) * size.x;
+            let z = aabb_center.z + (row as f32 / (rows - 1) as f32 - 0.5) * size.y;
+            heights.push(terrain.height(Vec2::new(x, z)));
+        }
+    }
+
     cmd.spawn((
         Name::new("ground-heightfield"),
+        TrackEntity,
         RigidBody::Fixed,
         ColliderScale::Absolute(Vec3::ONE),
         CollisionGroups::new(STATIC_GROUP, Group::ALL),
         Friction::coefficient(3.),
         // Restitution::coefficient(0.05),
         Restitution::coefficient(0.),
-        Collider::heightfield(
-            vec![0.; rows * cols],
-            rows,
-            cols,
-            Vec3::new(size.x, 0., size.y),
-        ),
-        Transform::from_translation(aabb_center),
+        Surface::Grass,
+        Collider::heightfield(heights, rows, cols, Vec3::new(size.x, 1., size.y)),
+        Transform::from_xyz(aabb_center.x, 0., aabb_center.z),
     ));
 }
@@ -1,136 +1,327 @@
-use super::track::Track;
+use super::track::{Track, TrackEntity};
 use crate::material::MaterialHandle;
+use crate::{Surface, TrackAsset};
 use bevy::asset::RenderAssetUsages;
 use bevy::light::NotShadowCaster;
 use bevy::mesh::{Indices, PrimitiveTopology, VertexAttributeValues};
 use bevy::prelude::*;
 use bevy_garage_car::STATIC_GROUP;
 use bevy_rapier3d::{na::Point3, prelude::*, rapier::prelude::ColliderShape};
-use std::ops::Sub;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+
+/// Length of one red and white stripe pair of the kerb texture.
+const KERB_TEXTURE_LENGTH: f32 = 10.;
+const KERB_HEIGHT: f32 = 0.002;
+const KERB_TOOTH_LENGTH: f32 = 0.5;
+
+/// Cross section of a kerb, the collider follows it so the car feels it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum KerbProfile {
+    /// Slight ramp up towards the outer edge.
+    Flat,
+    /// Tall rounded bump that throws the car out of line.
+    Sausage,
+    /// Ridges across the kerb that rattle the suspension.
+    SawTooth,
+}
+
+impl KerbProfile {
+    /// Height above the road, across from 0 at the road edge to 1 at the outer edge, along in meters.
+    pub fn height(self, across: f32, along: f32) -> f32 {
+        match self {
+            KerbProfile::Flat => 0.02 * across,
+            KerbProfile::Sausage => 0.1 * (PI * across).sin(),
+            KerbProfile::SawTooth => {
+                let f = (along / KERB_TOOTH_LENGTH).fract();
+                0.04 * (f / 0.75).min((1. - f) / 0.25)
+            }
+        }
+    }
+    fn columns(self) -> usize {
+        match self {
+            KerbProfile::Sausage => 8,
+            KerbProfile::Flat | KerbProfile::SawTooth => 1,
+        }
+    }
+    /// Max distance between mesh rows along the kerb.
+    fn step(self) -> f32 {
+        match self {
+            KerbProfile::SawTooth => KERB_TOOTH_LENGTH / 4.,
+            KerbProfile::Flat | KerbProfile::Sausage => f32::MAX,
+        }
+    }
+}
+
+/// Kerbs placed at corners from the centerline curvature.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KerbSettings {
+    /// Corners tighter than this radius in meters get kerbs.
+    pub max_radius: f32,
+    /// Kerb on the inside of the corner, centered on the apex.
+    pub apex_length: f32,
+    /// Kerb on the outside, from the corner exit onwards.
+    pub exit_length: f32,
+    pub width: f32,
+    pub apex_profile: KerbProfile,
+    pub exit_profile: KerbProfile,
+}
+
+impl Default for KerbSettings {
+    fn default() -> Self {
+        Self {
+            max_radius: 150.,
+            apex_length: 20.,
+            exit_length: 25.,
+            width: 1.,
+            apex_profile: KerbProfile::SawTooth,
+            exit_profile: KerbProfile::Flat,
+        }
+    }
+}
+
+/// Kerbs around a control point, halfway to its neighbours, instead of the generated ones.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct KerbOverride {
+    #[serde(default)]
+    pub left: bool,
+    #[serde(default)]
+    pub right: bool,
+    /// Apex profile of the track when not set.
+    #[serde(default)]
+    pub profile: Option<KerbProfile>,
+}
+
+/// Kerb along the track points `from..=to` on one side of the road.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KerbStrip {
+    pub left: bool,
+    pub from: usize,
+    pub to: usize,
+    pub profile: KerbProfile,
+}
+
+/// Finds the kerbs of a track: inside the apex and outside the exit of every corner.
+pub fn kerb_strips(
+    track: &Track,
+    settings: &KerbSettings,
+    overrides: &[Option<KerbOverride>],
+) -> Vec<KerbStrip> {
+    let n = track.points.len();
+    if n < 2 {
+        return vec![];
+    }
+    let mut distances: Vec<f32> = vec![0.];
+    for w in track.points.windows(2) {
+        distances.push(distances.last().unwrap() + w[0].distance(w[1]));
+    }
+    let length = distances[n - 1];
+    let mut left: Vec<Option<KerbProfile>> = vec![None; n];
+    let mut right: Vec<Option<KerbProfile>> = vec![None; n];
+    let mut mark = |side_left: bool, from: f32, to: f32, profile: KerbProfile| {
+        let side = if side_left { &mut left } else { &mut right };
+        for (j, d) in distances.iter().enumerate() {
+            let inside = match track.closed {
+                true => (d - from).rem_euclid(length) <= to - from,
+                false => (from..=to).contains(d),
+            };
+            if inside {
+                side[j] = Some(profile);
+            }
+        }
+    };
+
+    // closed tracks repeat the first point last, the scan starts on a straight so no corner wraps
+    let m = if track.closed { n - 1 } else { n };
+    let threshold = 1. / settings.max_radius;
+    let curvature = |k: usize| track.curvature[k % m];
+    let offset = match track.closed {
+        true => (0..m)
+            .find(|&k| curvature(k).abs() < threshold)
+            .unwrap_or(0),
+        false => 0,
+    };
+    let mut k = offset;
+    while k < offset + m {
+        let sign = curvature(k).signum();
+        if curvature(k).abs() < threshold {
+            k += 1;
+            continue;
+        }
+        let start = k;
+        while k < offset + m && curvature(k).abs() >= threshold && curvature(k).signum() == sign {
+            k += 1;
+        }
+        let apex = (start..k)
+            .max_by(|a, b| curvature(*a).abs().total_cmp(&curvature(*b).abs()))
+            .unwrap_or(start);
+        let (apex, exit) = (distances[apex % m], distances[(k - 1) % m]);
+        // turning left puts the inside of the corner on the left
+        let inside_left = sign > 0.;
+        mark(
+            inside_left,
+            apex - settings.apex_length / 2.,
+            apex + settings.apex_length / 2.,
+            settings.apex_profile,
+        );
+        mark(
+            !inside_left,
+            exit,
+            exit + settings.exit_length,
+            settings.exit_profile,
+        );
+    }
+
+    for (j, kerb) in overrides.iter().enumerate().take(n) {
+        if let Some(kerb) = kerb {
+            let profile = kerb.profile.unwrap_or(settings.apex_profile);
+            left[j] = kerb.left.then_some(profile);
+            right[j] = kerb.right.then_some(profile);
+        }
+    }
+
+    let mut strips: Vec<KerbStrip> = vec![];
+    for (side_left, side) in [(true, &left), (false, &right)] {
+        let mut j = 0;
+        while j < n {
+            let Some(profile) = side[j] else {
+                j += 1;
+                continue;
+            };
+            let from = j;
+            while j + 1 < n && side[j + 1] == Some(profile) {
+                j += 1;
+            }
+            if j > from {
+                strips.push(KerbStrip {
+                    left: side_left,
+                    from,
+                    to: j,
+                    profile,
+                });
+            }
+            j += 1;
+        }
+    }
+    strips
+}
 
 pub fn spawn_kerb(
     cmd: &mut Commands,
     meshes: &mut ResMut<Assets<Mesh>>,
     handled_materials: &Res<MaterialHandle>,
     track: &Track,
+    track_asset: &TrackAsset,
 ) {
-    let kerb_length: f32 = 10.;
-    let kerb_height: f32 = 0.002;
-    let from_center: f32 = 5.;
-    let top_norm = Vec3::Y;
-
-    let normals_side = &track.left_norm;
-    let mut vertices: Vec<[f32; 3]> = vec![];
-    let mut normals: Vec<[f32; 3]> = Vec::new();
-    let mut uvs: Vec<[f32; 2]> = Vec::new();
-    let mut len: f32 = 0.;
-    for (i, p) in track.points.iter().enumerate() {
-        let last: bool = i + 1 == track.points.len();
-        let i_next: usize = if last { 0 } else { i + 1 };
-        let point: Vec3 = *p + normals_side[i] * from_center;
-        let point_next: Vec3 = track.points[i_next] + normals_side[i_next] * from_center;
-        let (v1, v2) = (point + normals_side[i], point);
-        vertices.push(v1.into());
-        vertices.push(v2.into());
-        let diff = point_next.sub(point).length();
-        let uv = len / kerb_length;
-        uvs.push([uv, 0.]);
-        uvs.push([uv, 1.]);
-        normals.push(top_norm.to_array());
-        normals.push(top_norm.to_array());
-        len += diff;
+    let settings = &track_asset.kerbs;
+    for strip in kerb_strips(track, settings, &track_asset.kerb_overrides()) {
+        let (vertices, indices) = kerb_geometry(track, &strip, settings.width);
+        let mut mesh = Mesh::new(
+            PrimitiveTopology::TriangleList,
+            RenderAssetUsages::RENDER_WORLD,
+        );
+        let uvs: Vec<[f32; 2]> = vertices.iter().map(|(_, uv)| *uv).collect();
+        let vertices: Vec<[f32; 3]> = vertices.iter().map(|(v, _)| *v).collect();
+        mesh.insert_attribute(
+            Mesh::ATTRIBUTE_POSITION,
+            VertexAttributeValues::from(vertices.clone()),
+        );
+        mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, VertexAttributeValues::from(uvs));
+        mesh.insert_indices(Indices::U32(indices.iter().flatten().copied().collect()));
+        mesh.compute_smooth_normals();
+
+        cmd.spawn((
+            Mesh3d(meshes.add(mesh)),
+            MeshMaterial3d(handled_materials.kerb.clone()),
+            Transform::from_xyz(0., KERB_HEIGHT, 0.),
+            Collider::from(
+                ColliderShape::trimesh(
+                    vertices
+                        .iter()
+                        .map(|v| Point3::new(v[0], v[1], v[2]))
+                        .collect(),
+                    indices,
+                )
+                .unwrap(),
+            ),
+            Friction {
+                combine_rule: CoefficientCombineRule::Average,
+                coefficient: 3.,
+            },
+            NotShadowCaster,
+            ColliderScale::Absolute(Vec3::ONE),
+            CollisionGroups::new(STATIC_GROUP, Group::ALL),
+            Restitution::coefficient(0.),
+            Surface::Kerb,
+            TrackEntity,
+        ));
+    }
+}
+
+/// Kerb vertex position and uv.
+type KerbVertex = ([f32; 3], [f32; 2]);
+
+/// Kerb surface as rows across the road edge, with uvs, and its triangles.
+fn kerb_geometry(track: &Track, strip: &KerbStrip, width: f32) -> (Vec<KerbVertex>, Vec<[u32; 3]>) {
+    let mut distances: Vec<f32> = vec![0.];
+    for j in strip.from..strip.to {
+        distances.push(distances.last().unwrap() + track.points[j].distance(track.points[j + 1]));
     }
-    let mut mesh = Mesh::new(
-        PrimitiveTopology::TriangleList,
-        RenderAssetUsages::RENDER_WORLD,
-    );
-    mesh.insert_attribute(
-        Mesh::ATTRIBUTE_POSITION,
-        VertexAttributeValues::from(vertices.clone()),
-    );
-    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, VertexAttributeValues::from(normals));
-    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, VertexAttributeValues::from(uvs));
-    mesh.insert_indices(Indices::U32(track.indices.clone()));
-
-    cmd.spawn((
-        Mesh3d(meshes.add(mesh.clone())),
-        MeshMaterial3d(handled_materials.kerb.clone()),
-        Transform::from_xyz(0., kerb_height, 0.),
-        Collider::from(
-            ColliderShape::trimesh(
-                vertices
-                    .iter()
-                    .map(|v| Point3::new(v[0], v[1], v[2]))
-                    .collect(),
-                track.collider_indices.clone(),
-            )
-            .unwrap(),
-        ),
-        Friction {
-            combine_rule: CoefficientCombineRule::Average,
-            coefficient: 3.,
-            ..default()
-        },
-        NotShadowCaster,
-        ColliderScale::Absolute(Vec3::ONE),
-        CollisionGroups::new(STATIC_GROUP, Group::ALL),
-        Restitution::coefficient(0.),
-    ));
-
-    let normals_side = &track.right_norm;
-    let mut vertices: Vec<[f32; 3]> = vec![];
-    let mut normals: Vec<[f32; 3]> = Vec::new();
-    let mut uvs: Vec<[f32; 2]> = Vec::new();
-    let mut len: f32 = 0.;
-    for (i, p) in track.points.iter().enumerate() {
-        let last: bool = i + 1 == track.points.len();
-        let i_next: usize = if last { 0 } else { i + 1 };
-        let point: Vec3 = *p + normals_side[i] * from_center;
-        let point_next: Vec3 = track.points[i_next] + normals_side[i_next] * from_center;
-        let (v1, v2) = (point, point + normals_side[i]);
-        vertices.push(v1.into());
-        vertices.push(v2.into());
-        let diff = point_next.sub(point).length();
-        uvs.push([len / kerb_length, 0.]);
-        uvs.push([len / kerb_length, 1.]);
-        normals.push(top_norm.to_array());
-        normals.push(top_norm.to_array());
-        len += diff;
+    let length = *distances.last().unwrap();
+    // rows at every track point plus every profile step between them
+    let mut rows: Vec<f32> = distances.clone();
+    let step = strip.profile.step();
+    let mut along = step;
+    while along < length {
+        rows.push(along);
+        along += step;
+    }
+    rows.sort_by(f32::total_cmp);
+    rows.dedup_by(|a, b| (*a - *b).abs() < 0.01);
+
+    let columns = strip.profile.columns();
+    let mut vertices: Vec<KerbVertex> = vec![];
+    let mut segment = 0;
+    for along in rows.iter() {
+        while segment + 1 < distances.len() - 1 && distances[segment + 1] < *along {
+            segment += 1;
+        }
+        let (a, b) = (strip.from + segment, strip.from + segment + 1);
+        let span = distances[segment + 1] - distances[segment];
+        let t = match span > 0. {
+            true => ((along - distances[segment]) / span).clamp(0., 1.),
+            false => 0.,
+        };
+        let side = match strip.left {
+            true => track.left_norm[a].lerp(track.left_norm[b], t),
+            false => track.right_norm[a].lerp(track.right_norm[b], t),
+        }
+        .normalize_or_zero();
+        let up = track.normals[a]
+            .lerp(track.normals[b], t)
+            .normalize_or(Vec3::Y);
+        let edge = track.points[a].lerp(track.points[b], t)
+            + side * (track.width[a] + (track.width[b] - track.width[a]) * t);
+        for c in 0..=columns {
+            // columns run from left to right so the triangles face up on both sides
+            let across = match strip.left {
+                true => 1. - c as f32 / columns as f32,
+                false => c as f32 / columns as f32,
+            };
+            let v = edge + side * across * width + up * strip.profile.height(across, *along);
+            vertices.push((v.into(), [along / KERB_TEXTURE_LENGTH, across]));
+        }
+    }
+
+    let stride = columns as u32 + 1;
+    let mut indices: Vec<[u32; 3]> = vec![];
+    for r in 0..rows.len() as u32 - 1 {
+        for c in 0..columns as u32 {
+            let (i, next) = (r * stride + c, (r + 1) * stride + c);
+            indices.push([i, i + 1, next]);
+            indices.push([next, i + 1, next + 1]);
+        }
     }
-    let mut mesh = Mesh::new(
-        PrimitiveTopology::TriangleList,
-        RenderAssetUsages::RENDER_WORLD,
-    );
-    mesh.insert_attribute(
-        Mesh::ATTRIBUTE_POSITION,
-        VertexAttributeValues::from(vertices.clone()),
-    );
-    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, VertexAttributeValues::from(normals));
-    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, VertexAttributeValues::from(uvs));
-    mesh.insert_indices(Indices::U32(track.indices.clone()));
-
-    cmd.spawn((
-        Mesh3d(meshes.add(mesh)),
-        MeshMaterial3d(handled_materials.kerb.clone()),
-        Transform::from_xyz(0., kerb_height, 0.),
-        Collider::from(
-            ColliderShape::trimesh(
-                vertices
-                    .iter()
-                    .map(|v| Point3::new(v[0], v[1], v[2]))
-                    .collect(),
-                track.collider_indices.clone(),
-            )
-            .unwrap(),
-        ),
-        Friction {
-            combine_rule: CoefficientCombineRule::Average,
-            coefficient: 3.,
-            ..default()
-        },
-        NotShadowCaster,
-        ColliderScale::Absolute(Vec3::ONE),
-        CollisionGroups::new(STATIC_GROUP, Group::ALL),
-        Restitution::coefficient(0.),
-    ));
+    (vertices, indices)
 }
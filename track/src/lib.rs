@@ -1,34 +1,74 @@
 pub mod asphalt;
+pub mod asset;
+pub mod branch;
 pub mod car_track;
 pub mod config;
 pub mod decor;
+pub mod generator;
+pub mod grid;
 pub mod ground;
 pub mod kerb;
+pub mod limits;
+pub mod map;
 pub mod material;
 pub mod mesh;
 pub mod progress;
 pub mod quality;
+pub mod racing_line;
+pub mod registry;
+pub mod rubber;
+pub mod scenery;
+pub mod session;
 pub mod shader;
+pub mod skid;
+pub mod spline;
+pub mod surface;
+pub mod timing;
 pub mod track;
+pub mod validate;
 pub mod wall;
+pub mod weather;
 
 pub use asphalt::*;
+pub use asset::*;
 use bevy_garage_car::CarSet;
+pub use branch::*;
 pub use car_track::*;
 pub use config::*;
 pub use decor::*;
+pub use generator::*;
+pub use grid::*;
 pub use ground::*;
+pub use limits::*;
+pub use map::*;
 pub use material::*;
 pub use progress::*;
 pub use quality::*;
+pub use racing_line::*;
+pub use registry::*;
+pub use rubber::*;
+pub use scenery::*;
+pub use session::*;
 pub use shader::*;
+pub use skid::*;
+pub use spline::*;
+pub use surface::*;
+pub use timing::*;
 pub use track::*;
+pub use validate::*;
+pub use weather::*;
 
 use bevy::prelude::*;
 
 pub use self::{
-    asphalt::spawn_road, ground::spawn_ground_heightfield, kerb::spawn_kerb, track::Track,
-    wall::spawn_walls,
+    asphalt::spawn_road,
+    ground::spawn_ground_heightfield,
+    kerb::{kerb_strips, spawn_kerb, KerbOverride, KerbProfile, KerbSettings, KerbStrip},
+    track::Track,
+    wall::{
+        barrier_kinds, spawn_soft_barrier, spawn_walls, wall_indices_clear_of, wall_indices_of,
+        BarrierKind, BarrierSide, BarrierZone,
+    },
 };
 
 pub struct TrackPlugin;
@@ -37,16 +77,78 @@ impl Plugin for TrackPlugin {
     fn build(&self, app: &mut App) {
         app.insert_resource(TrackConfig::default())
             .add_plugins(ShadersPlugin)
+            .init_asset::<TrackAsset>()
+            .init_asset_loader::<TrackAssetLoader>()
             .init_resource::<MaterialHandle>()
+            .init_resource::<TrackRegistry>()
+            .init_resource::<TrackHandle>()
+            .init_resource::<TrackCarsRespawn>()
+            .init_resource::<TrackGeneratorTask>()
+            .init_resource::<TrackLimitsConfig>()
+            .init_resource::<Weather>()
+            .init_resource::<CullingSettings>()
+            .init_resource::<CellGrid>()
+            .init_resource::<RacingLineSettings>()
+            .init_resource::<RacingLine>()
+            .init_resource::<RacingLineTask>()
+            .init_resource::<GripField>()
+            .init_resource::<SkidMarks>()
+            .add_message::<TrackLoadedEvent>()
+            .add_message::<LoadTrack>()
+            .add_message::<SpawnCarOnTrackEvent>()
+            .add_message::<RespawnCar>()
+            .add_message::<StageFinished>()
+            .add_message::<LapCompleted>()
+            .add_message::<TrackLimitsViolation>()
             .add_systems(
-                Startup,
+                Update,
                 (
-                    track_polyline_start_system,
-                    track_start_system,
-                    track_decorations_start_system.after(track_polyline_start_system),
+                    track_cars_despawn_system
+                        .after(load_track_system)
+                        .before(track_asset_event_system),
+                    load_track_system,
+                    track_generator_start_system,
+                    track_generator_task_system,
+                    track_asset_event_system.after(load_track_system),
+                    (
+                        track_polyline_start_system,
+                        track_start_system,
+                        racing_line_start_system,
+                        rubber_start_system.after(track_polyline_start_system),
+                        skid_start_system,
+                        track_decorations_start_system.after(track_polyline_start_system),
+                        track_cars_respawn_system.after(track_polyline_start_system),
+                    )
+                        .run_if(on_message::<TrackLoadedEvent>)
+                        .after(track_asset_event_system),
                 ),
             )
-            .add_systems(Update, (far_culling, progress_system.in_set(CarSet::Input)));
+            .add_systems(
+                Update,
+                (
+                    cell_grid_system,
+                    far_culling.after(cell_grid_system),
+                    cell_lod_system.after(far_culling),
+                    cell_material_system.after(far_culling),
+                    racing_line_task_system,
+                    racing_line_gizmo_system.after(racing_line_task_system),
+                    respawn_car_system.before(progress_system),
+                    progress_system.in_set(CarSet::Input),
+                    lap_timer_system
+                        .in_set(CarSet::Input)
+                        .after(progress_system),
+                    track_limits_system
+                        .in_set(CarSet::Input)
+                        .after(lap_timer_system),
+                    speed_limit_system.after(CarSet::Input).before(CarSet::Esp),
+                    weather_system,
+                    wheel_surface_system.after(weather_system),
+                    surface_drag_system.after(wheel_surface_system),
+                    rubber_system.after(wheel_surface_system),
+                    rubber_texture_system.after(rubber_system),
+                    skid_system.after(wheel_surface_system),
+                ),
+            );
     }
 }
 
@@ -54,32 +156,114 @@ pub fn track_start_system(
     handled_materials: Res<MaterialHandle>,
     mut cmd: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
+    track_handle: Res<TrackHandle>,
+    tracks: Res<Assets<TrackAsset>>,
 ) {
-    let track = Track::new();
-    let aabb = spawn_road(&handled_materials, &mut cmd, &mut meshes, &track);
-    spawn_ground_heightfield(&mut cmd, &mut meshes, &handled_materials, &aabb, 100.);
-
-    spawn_kerb(&mut cmd, &mut meshes, &handled_materials, &track);
-    let mut left_wall_points: Vec<Vec3> = vec![];
-    let mut right_wall_points: Vec<Vec3> = vec![];
-    for (i, p) in track.points.iter().enumerate() {
-        left_wall_points.push(*p + track.right_norm[i] * 7.5);
-        right_wall_points.push(*p + track.right_norm[i] * -7.5);
+    let Some(track_asset) = tracks.get(&track_handle.0) else {
+        return;
+    };
+    for issue in validate_track(track_asset) {
+        println!("track {}: {issue}", track_asset.name);
     }
-    spawn_walls(
+    let track = Track::new(track_asset);
+    let branches: Vec<Track> = track_asset
+        .branches
+        .iter()
+        .map(|branch| {
+            let branch_asset = track_asset.branch_asset(branch);
+            for issue in validate_track(&branch_asset) {
+                println!("track {} branch {}: {issue}", track_asset.name, branch.name);
+            }
+            Track::new(&branch_asset)
+        })
+        .collect();
+    let aabb = spawn_road(&handled_materials, &mut cmd, &mut meshes, &track, true);
+    for branch in branches.iter() {
+        spawn_road(&handled_materials, &mut cmd, &mut meshes, branch, false);
+    }
+    let roads: Vec<&Track> = std::iter::once(&track).chain(branches.iter()).collect();
+    spawn_ground_heightfield(
         &mut cmd,
         &mut meshes,
         &handled_materials,
-        &track.indices,
-        &left_wall_points,
-        &track.right_norm,
+        &aabb,
+        &roads,
+        100.,
     );
-    spawn_walls(
+
+    spawn_kerb(
         &mut cmd,
         &mut meshes,
         &handled_materials,
-        &track.indices,
-        &right_wall_points,
-        &track.right_norm,
+        &track,
+        track_asset,
     );
+    for (road_i, road) in roads.iter().enumerate() {
+        // leave openings where branches split off and rejoin
+        let other_roads: Vec<&Track> = roads
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| *i != road_i)
+            .map(|(_, other)| *other)
+            .collect();
+        // barrier zones are measured along the main track, branches get concrete
+        let zones = match road_i {
+            0 => track_asset.barriers.as_slice(),
+            _ => &[],
+        };
+        let (left_wall_points, right_wall_points) = road.wall_points();
+        for (left, wall_points, outward) in [
+            (true, left_wall_points, &road.left_norm),
+            (false, right_wall_points, &road.right_norm),
+        ] {
+            let indices = wall_indices_clear_of(&road.indices, &wall_points, &other_roads);
+            let kinds = barrier_kinds(road, track_asset.start.into(), zones, left);
+            for kind in BarrierKind::BUILT {
+                let indices = wall_indices_of(&indices, &kinds, kind);
+                if indices.is_empty() {
+                    continue;
+                }
+                match kind.is_soft() {
+                    true => spawn_soft_barrier(
+                        &mut cmd,
+                        &mut meshes,
+                        &handled_materials,
+                        &indices,
+                        &wall_points,
+                        outward,
+                        kind,
+                    ),
+                    false => spawn_walls(
+                        &mut cmd,
+                        &mut meshes,
+                        &handled_materials,
+                        &indices,
+                        &wall_points,
+                        outward,
+                        kind,
+                    ),
+                }
+            }
+        }
+    }
+    if !track.closed {
+        let (left_wall_points, right_wall_points) = track.wall_points();
+        // cap the road ends of point-to-point tracks
+        for i in [0, track.points.len() - 1] {
+            // facing away from the road, back at the start and ahead at the end
+            let outward = match i {
+                0 => -track.left_norm[i].cross(track.normals[i]),
+                _ => track.left_norm[i].cross(track.normals[i]),
+            };
+            spawn_walls(
+                &mut cmd,
+                &mut meshes,
+                &handled_materials,
+                &[0, 1, 2, 2, 1, 3],
+                &[left_wall_points[i], right_wall_points[i]],
+                &[outward, outward],
+                BarrierKind::Concrete,
+            );
+        }
+    }
 }
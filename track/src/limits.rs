@@ -0,0 +1,139 @@
+use crate::{CarTrack, LapTimer, TrackConfig};
+use bevy::prelude::*;
+use bevy_garage_car::{CarWheels, Wheel};
+
+#[derive(Resource, Debug)]
+pub struct TrackLimitsConfig {
+    /// Width of the kerb outside the road edge that still counts as track.
+    pub kerb_width: f32,
+    /// Cuts gaining less distance than this are ignored.
+    pub min_gain: f32,
+    /// Warnings given before cuts are penalized.
+    pub warnings: u32,
+    /// Time penalty in seconds.
+    pub penalty: f32,
+}
+
+impl Default for TrackLimitsConfig {
+    fn default() -> Self {
+        Self {
+            kerb_width: 1.,
+            min_gain: 1.,
+            warnings: 3,
+            penalty: 5.,
+        }
+    }
+}
+
+#[derive(Component, Debug, Default)]
+pub struct TrackLimits {
+    pub warnings: u32,
+    /// Penalty time collected so far.
+    pub penalty: f32,
+    cut: Option<TrackCut>,
+    prev_position: Option<Vec3>,
+}
+
+#[derive(Debug)]
+struct TrackCut {
+    track_position: f32,
+    path: f32,
+    time: f32,
+}
+
+#[derive(Debug, Message)]
+pub struct TrackLimitsViolation {
+    pub car: Entity,
+    pub index: usize,
+    /// Track distance gained over driving the road.
+    pub distance_gained: f32,
+    pub time_gained: f32,
+    /// Time penalty, a warning when not set.
+    pub penalty: Option<f32>,
+}
+
+pub fn track_limits_system(
+    time: Res<Time>,
+    track_config: Res<TrackConfig>,
+    config: Res<TrackLimitsConfig>,
+    mut cars: Query<(
+        Entity,
+        &Transform,
+        &CarTrack,
+        &CarWheels,
+        &mut TrackLimits,
+        &mut LapTimer,
+    )>,
+    wheels: Query<&Transform, With<Wheel>>,
+    mut violations: MessageWriter<TrackLimitsViolation>,
+) {
+    if track_config.polyline.is_none() {
+        return;
+    }
+    for (e, transform, car_track, car_wheels, mut limits, mut lap_timer) in cars.iter_mut() {
+        let step = limits
+            .prev_position
+            .replace(transform.translation)
+            .map(|prev| prev.distance(transform.translation))
+            .unwrap_or(0.);
+
+        let off_track = car_track.branch.is_none()
+            && car_wheels.entities.iter().all(|wheel| {
+                wheels.get(*wheel).is_ok_and(|wheel| {
+                    !track_config.is_within_edges(
+                        wheel.translation,
+                        config.kerb_width,
+                        car_track.segment,
+                    )
+                })
+            });
+
+        if off_track {
+            let cut = limits.cut.get_or_insert(TrackCut {
+                track_position: car_track.track_position,
+                path: 0.,
+                time: 0.,
+            });
+            cut.path += step;
+            cut.time += time.delta_secs();
+            continue;
+        }
+        let Some(cut) = limits.cut.take() else {
+            continue;
+        };
+
+        let mut progress = car_track.track_position - cut.track_position;
+        if track_config.closed {
+            let length = track_config.track_length;
+            progress = (progress + length / 2.).rem_euclid(length) - length / 2.;
+        }
+        let distance_gained = progress - cut.path;
+        if distance_gained < config.min_gain {
+            continue;
+        }
+        let time_gained = if cut.path > 0. {
+            distance_gained * cut.time / cut.path
+        } else {
+            0.
+        };
+        lap_timer.valid = false;
+        limits.warnings += 1;
+        let penalty = (limits.warnings > config.warnings).then_some(config.penalty);
+        limits.penalty += penalty.unwrap_or(0.);
+        debug!(
+            "car {} track limits, gained {distance_gained:.1}m {time_gained:.2}s, {}",
+            car_track.index,
+            match penalty {
+                Some(penalty) => format!("{penalty:.0}s penalty"),
+                None => format!("warning {}", limits.warnings),
+            }
+        );
+        violations.write(TrackLimitsViolation {
+            car: e,
+            index: car_track.index,
+            distance_gained,
+            time_gained,
+            penalty,
+        });
+    }
+}
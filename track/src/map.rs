@@ -0,0 +1,278 @@
+use crate::{project_on_polyline, Track, TrackAsset};
+use bevy::prelude::*;
+use bevy_rapier3d::na::Point3;
+use bevy_rapier3d::parry::shape::Polyline;
+use std::fmt::Write;
+
+/// Corners tighter than this radius in meters get a number.
+const CORNER_RADIUS: f32 = 100.;
+/// Bends closer than this in meters count as one corner.
+const CORNER_GAP: f32 = 30.;
+/// Bends turning less than this in degrees aren't numbered.
+const MIN_CORNER_TURN: f32 = 15.;
+/// Room around the track for the corner numbers, part of the larger side of the map.
+const MAP_PADDING: f32 = 0.06;
+/// Roads narrower than this on the raster are drawn this wide, in pixels.
+const MIN_ROAD_PIXELS: f32 = 1.5;
+const SECTOR_COLORS: [[u8; 3]; 3] = [[225, 70, 70], [70, 140, 225], [235, 195, 50]];
+
+/// Top-down outline of a track, map x is world x and map y is world z,
+/// so north is -Z like the default camera.
+#[derive(Debug, Clone, Default)]
+pub struct TrackMap {
+    pub closed: bool,
+    pub center: Vec<Vec2>,
+    pub left: Vec<Vec2>,
+    pub right: Vec<Vec2>,
+    /// Sector of every track point, counted from the start line.
+    pub sectors: Vec<usize>,
+    /// Road edges at the start line.
+    pub start: (Vec2, Vec2),
+    /// Label positions beside the apex of every numbered corner, in driving order.
+    pub corners: Vec<Vec2>,
+    pub min: Vec2,
+    pub max: Vec2,
+}
+
+impl TrackMap {
+    pub fn new(track_asset: &TrackAsset) -> Self {
+        let track = Track::new(track_asset);
+        let n = track.points.len();
+        if n < 2 {
+            return Self::default();
+        }
+        let polyline = Polyline::new(
+            track
+                .points
+                .iter()
+                .map(|p| Point3::new(p.x, p.y, p.z))
+                .collect(),
+            None,
+        );
+        let mut segments: Vec<f32> = vec![];
+        let mut length = 0.;
+        for s in polyline.segments() {
+            segments.push(length);
+            length += s.length();
+        }
+        let from_start = |distance: f32, start: f32| match track.closed {
+            true => (distance - start).rem_euclid(length),
+            false => distance - start,
+        };
+        let start = project_on_polyline(&polyline, &segments, track_asset.start.into());
+        let meters: Vec<f32> = (0..n)
+            .map(|i| from_start(segments.get(i).copied().unwrap_or(length), start.distance))
+            .collect();
+        let mut boundaries: Vec<f32> = match track_asset.sectors.is_empty() {
+            true => vec![length / 3., length * 2. / 3.],
+            false => track_asset
+                .sectors
+                .iter()
+                .map(|p| {
+                    let distance = project_on_polyline(&polyline, &segments, (*p).into()).distance;
+                    from_start(distance, start.distance)
+                })
+                .collect(),
+        };
+        boundaries.sort_by(f32::total_cmp);
+
+        let xz = |points: &[Vec3]| points.iter().map(|p| p.xz()).collect::<Vec<Vec2>>();
+        let (center, left, right) = (xz(&track.points), xz(&track.left), xz(&track.right));
+        let edge = |edge: &[Vec2]| {
+            let j = (start.segment + 1).min(n - 1);
+            edge[start.segment].lerp(edge[j], start.t)
+        };
+        let corners: Vec<Vec2> = corner_apexes(&track, &meters)
+            .into_iter()
+            .map(|(apex, outside_left)| {
+                let outside = if outside_left {
+                    left[apex]
+                } else {
+                    right[apex]
+                };
+                let direction = (outside - center[apex]).normalize_or_zero();
+                outside + direction * length * 0.01
+            })
+            .collect();
+
+        let (mut min, mut max) = (Vec2::MAX, Vec2::MIN);
+        for p in left.iter().chain(right.iter()).chain(corners.iter()) {
+            min = min.min(*p);
+            max = max.max(*p);
+        }
+        let padding = Vec2::splat((max - min).max_element() * MAP_PADDING);
+        TrackMap {
+            closed: track.closed,
+            sectors: meters
+                .iter()
+                .map(|m| boundaries.partition_point(|b| b <= m))
+                .collect(),
+            start: (edge(&left), edge(&right)),
+            center,
+            left,
+            right,
+            corners,
+            min: min - padding,
+            max: max + padding,
+        }
+    }
+    /// Position on a square map from 0 to 1, the track centered on its larger side.
+    pub fn uv(&self, p: Vec2) -> Vec2 {
+        let size = (self.max - self.min).max_element().max(f32::EPSILON);
+        (p - (self.min + self.max) / 2.) / size + 0.5
+    }
+    pub fn to_svg(&self, size: f32) -> String {
+        let px = |p: Vec2| self.uv(p) * size;
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{size}\" height=\"{size}\" viewBox=\"0 0 {size} {size}\">\n\
+             <rect width=\"{size}\" height=\"{size}\" fill=\"#202020\"/>\n"
+        );
+        // one outline per run of points in the same sector
+        let mut from = 0;
+        while from + 1 < self.center.len() {
+            let sector = self.sectors[from];
+            let mut to = from + 1;
+            while to + 1 < self.center.len() && self.sectors[to] == sector {
+                to += 1;
+            }
+            let outline: Vec<String> = self.left[from..=to]
+                .iter()
+                .chain(self.right[from..=to].iter().rev())
+                .map(|p| {
+                    let p = px(*p);
+                    format!("{:.1},{:.1}", p.x, p.y)
+                })
+                .collect();
+            let [r, g, b] = SECTOR_COLORS[sector % SECTOR_COLORS.len()];
+            let _ = writeln!(
+                svg,
+                "<polygon points=\"{}\" fill=\"rgb({r},{g},{b})\" stroke=\"rgb({r},{g},{b})\" stroke-width=\"1\"/>",
+                outline.join(" ")
+            );
+            from = to;
+        }
+        let (a, b) = (px(self.start.0), px(self.start.1));
+        let _ = writeln!(
+            svg,
+            "<line x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\" stroke=\"white\" stroke-width=\"3\"/>",
+            a.x, a.y, b.x, b.y
+        );
+        let radius = size * 0.012;
+        for (i, corner) in self.corners.iter().enumerate() {
+            let p = px(*corner);
+            let _ = writeln!(
+                svg,
+                "<circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"{radius:.1}\" fill=\"white\"/>\n\
+                 <text x=\"{:.1}\" y=\"{:.1}\" font-family=\"sans-serif\" font-size=\"{:.1}\" text-anchor=\"middle\" dominant-baseline=\"central\">{}</text>",
+                p.x,
+                p.y,
+                p.x,
+                p.y,
+                radius * 1.3,
+                i + 1
+            );
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+    /// Road colored by sector with the start line on a transparent square, rgba rows from the top.
+    pub fn rasterize(&self, size: u32) -> Vec<u8> {
+        let mut pixels = vec![0; (size * size * 4) as usize];
+        let scale = size as f32 / (self.max - self.min).max_element().max(f32::EPSILON);
+        let px = |p: Vec2| self.uv(p) * size as f32;
+        for i in 0..self.center.len().saturating_sub(1) {
+            let width = self.left[i].distance(self.right[i]) * scale / 2.;
+            let [r, g, b] = SECTOR_COLORS[self.sectors[i] % SECTOR_COLORS.len()];
+            stamp(
+                &mut pixels,
+                size,
+                (px(self.center[i]), px(self.center[i + 1])),
+                width.max(MIN_ROAD_PIXELS),
+                [r, g, b, 255],
+            );
+        }
+        let (a, b) = (px(self.start.0), px(self.start.1));
+        let across = (b - a).normalize_or_zero() * (MIN_ROAD_PIXELS + 1.);
+        stamp(&mut pixels, size, (a - across, b + across), 1., [255; 4]);
+        pixels
+    }
+}
+
+/// Fills the pixels within a radius of a segment.
+fn stamp(pixels: &mut [u8], size: u32, (a, b): (Vec2, Vec2), radius: f32, color: [u8; 4]) {
+    let (min, max) = (a.min(b) - radius, a.max(b) + radius);
+    let clamp = |v: f32| (v.max(0.) as u32).min(size - 1);
+    for y in clamp(min.y)..=clamp(max.y) {
+        for x in clamp(min.x)..=clamp(max.x) {
+            let p = Vec2::new(x as f32 + 0.5, y as f32 + 0.5);
+            let ab = b - a;
+            let t = match ab.length_squared() > 0. {
+                true => ((p - a).dot(ab) / ab.length_squared()).clamp(0., 1.),
+                false => 0.,
+            };
+            if p.distance(a + ab * t) <= radius {
+                let i = ((y * size + x) * 4) as usize;
+                pixels[i..i + 4].copy_from_slice(&color);
+            }
+        }
+    }
+}
+
+/// Apex point of every corner, ordered from the start line, and whether its outside is on the left.
+fn corner_apexes(track: &Track, meters: &[f32]) -> Vec<(usize, bool)> {
+    let n = track.points.len();
+    // closed tracks repeat the first point last, the scan starts on a straight so no corner wraps
+    let m = if track.closed { n - 1 } else { n };
+    let threshold = 1. / CORNER_RADIUS;
+    let curvature = |k: usize| track.curvature[k % m];
+    let offset = match track.closed {
+        true => (0..m)
+            .find(|&k| curvature(k).abs() < threshold / 2.)
+            .unwrap_or(0),
+        false => 0,
+    };
+    let step = |k: usize| track.points[k % m].distance(track.points[(k + 1) % m]);
+    // runs of points turning the same way, from..to in scan order
+    let mut runs: Vec<(usize, usize, f32)> = vec![];
+    let mut k = offset;
+    while k < offset + m {
+        let sign = curvature(k).signum();
+        if curvature(k).abs() < threshold {
+            k += 1;
+            continue;
+        }
+        let from = k;
+        // corners end on a lower threshold so a dip at mid corner doesn't split them
+        while k < offset + m
+            && curvature(k).abs() >= threshold / 2.
+            && curvature(k).signum() == sign
+        {
+            k += 1;
+        }
+        match runs.last_mut() {
+            Some(last) if last.2 == sign && (last.1..from).map(step).sum::<f32>() < CORNER_GAP => {
+                last.1 = k;
+            }
+            _ => runs.push((from, k, sign)),
+        }
+    }
+    let mut apexes: Vec<(usize, bool)> = vec![];
+    for (from, to, sign) in runs {
+        let turn: f32 = (from..to).map(|k| curvature(k).abs() * step(k)).sum();
+        if turn < MIN_CORNER_TURN.to_radians() {
+            continue;
+        }
+        // the apex label goes where half of the turn is done
+        let mut turned = 0.;
+        let apex = (from..to)
+            .find(|k| {
+                turned += curvature(*k).abs() * step(*k);
+                turned >= turn / 2.
+            })
+            .unwrap_or(from);
+        // turning left puts the outside on the right
+        apexes.push((apex % m, sign < 0.));
+    }
+    apexes.sort_by(|a, b| meters[a.0].total_cmp(&meters[b.0]));
+    apexes
+}
@@ -18,10 +18,15 @@ pub type HandleGround = Handle<ExtendedMaterialGround>;
 pub struct MaterialHandle {
     pub asphalt: Handle<ExtendedMaterialAsphalt>,
     pub ground: Handle<ExtendedMaterialGround>,
-    pub asphalt_color: Handle<StandardMaterial>,
-    pub ground_color: Handle<StandardMaterial>,
+    /// Same look without the shader detail, for cells far from the camera.
+    pub asphalt_far: Handle<ExtendedMaterialAsphalt>,
+    pub ground_far: Handle<ExtendedMaterialGround>,
     pub wall: Handle<StandardMaterial>,
     pub kerb: Handle<StandardMaterial>,
+    pub armco: Handle<StandardMaterial>,
+    pub catch_fence: Handle<StandardMaterial>,
+    pub tire_wall: Handle<StandardMaterial>,
+    pub tecpro: Handle<StandardMaterial>,
 }
 
 impl FromWorld for MaterialHandle {
@@ -35,14 +40,19 @@ impl FromWorld for MaterialHandle {
 
         let ground_color: Color = Srgba::hex("6aa84f").unwrap().into();
         let mut ground_materials = world.resource_mut::<Assets<ExtendedMaterialGround>>();
+        let ground_base = StandardMaterial {
+            base_color: ground_color,
+            depth_bias: 0.,
+            ..Default::default()
+        };
         let ground_handle = ground_materials.add(ExtendedMaterial {
-            base: StandardMaterial {
-                base_color: ground_color,
-                depth_bias: 0.,
-                ..Default::default()
-            },
+            base: ground_base.clone(),
             extension: GroundExtension { quality },
         });
+        let ground_far_handle = ground_materials.add(ExtendedMaterial {
+            base: ground_base,
+            extension: GroundExtension { quality: 0 },
+        });
 
         #[cfg(target_arch = "wasm32")]
         let asphalt_depth_bias = 1.;
@@ -51,13 +61,26 @@ impl FromWorld for MaterialHandle {
 
         let asphalt_color: Color = Srgba::hex("333355").unwrap().into();
         let mut asphalt_materials = world.resource_mut::<Assets<ExtendedMaterialAsphalt>>();
+        let asphalt_base = StandardMaterial {
+            base_color: asphalt_color,
+            depth_bias: asphalt_depth_bias,
+            ..Default::default()
+        };
         let asphalt_handle = asphalt_materials.add(ExtendedMaterial {
-            base: StandardMaterial {
-                base_color: asphalt_color,
-                depth_bias: asphalt_depth_bias,
-                ..Default::default()
+            base: asphalt_base.clone(),
+            extension: AsphaltExtension {
+                quality,
+                wetness: 0.,
+                rubber: None,
+            },
+        });
+        let asphalt_far_handle = asphalt_materials.add(ExtendedMaterial {
+            base: asphalt_base,
+            extension: AsphaltExtension {
+                quality: 0,
+                wetness: 0.,
+                rubber: None,
             },
-            extension: AsphaltExtension { quality },
         });
 
         let mut images = world.resource_mut::<Assets<Image>>();
@@ -65,20 +88,6 @@ impl FromWorld for MaterialHandle {
         let kerb_image_handle = images.add(kerb_texture());
 
         let mut standard_materials = world.resource_mut::<Assets<StandardMaterial>>();
-        let asphalt_color_handle = standard_materials.add(StandardMaterial {
-            base_color: asphalt_color,
-            depth_bias: asphalt_depth_bias,
-            reflectance: 0.5,
-            perceptual_roughness: 0.7,
-            ..default()
-        });
-        let ground_color_handle = standard_materials.add(StandardMaterial {
-            base_color: ground_color,
-            depth_bias: 0.,
-            reflectance: 0.5,
-            perceptual_roughness: 0.75,
-            ..default()
-        });
         let wall_handle = standard_materials.add(StandardMaterial {
             base_color_texture: Some(wall_image_handle),
             perceptual_roughness: 0.7,
@@ -91,14 +100,40 @@ impl FromWorld for MaterialHandle {
             depth_bias: 1.,
             ..default()
         });
+        let armco_handle = standard_materials.add(StandardMaterial {
+            base_color: Color::srgb(0.75, 0.77, 0.8),
+            metallic: 0.8,
+            perceptual_roughness: 0.4,
+            ..default()
+        });
+        let catch_fence_handle = standard_materials.add(StandardMaterial {
+            base_color: Color::srgba(0.6, 0.6, 0.6, 0.35),
+            alpha_mode: AlphaMode::Blend,
+            cull_mode: None,
+            ..default()
+        });
+        let tire_wall_handle = standard_materials.add(StandardMaterial {
+            base_color: Color::srgb(0.05, 0.05, 0.05),
+            perceptual_roughness: 0.9,
+            ..default()
+        });
+        let tecpro_handle = standard_materials.add(StandardMaterial {
+            base_color: Color::srgb(0.1, 0.3, 0.8),
+            perceptual_roughness: 0.8,
+            ..default()
+        });
 
         Self {
             asphalt: asphalt_handle,
-            asphalt_color: asphalt_color_handle,
+            asphalt_far: asphalt_far_handle,
             ground: ground_handle,
-            ground_color: ground_color_handle,
+            ground_far: ground_far_handle,
             kerb: kerb_handle,
             wall: wall_handle,
+            armco: armco_handle,
+            catch_fence: catch_fence_handle,
+            tire_wall: tire_wall_handle,
+            tecpro: tecpro_handle,
         }
     }
 }
@@ -1,27 +1,45 @@
-use crate::car_track::CarTrack;
-use crate::{TrackConfig, TRACK_POSITIONS};
+use crate::car_track::{CarTrack, StageFinished};
+use crate::{
+    project_on_polyline, RacingLine, Track, TrackAsset, TrackBranchConfig, TrackConfig,
+    TrackEntity, TrackHandle,
+};
 use bevy::prelude::*;
 use bevy_garage_car::{CarRes, CAR_TRAINING_GROUP, STATIC_GROUP};
 use bevy_rapier3d::parry::query::PointQueryWithLocation;
 use bevy_rapier3d::parry::shape::{Polyline, SegmentPointLocation};
 use bevy_rapier3d::prelude::Real;
 use bevy_rapier3d::{na::Point3, prelude::*, rapier::prelude::ColliderShape};
-use std::cmp::Ordering;
 
-pub fn track_polyline_start_system(mut cmd: Commands, mut track_config: ResMut<TrackConfig>) {
-    let positions = TRACK_POSITIONS;
+pub fn track_polyline_start_system(
+    mut cmd: Commands,
+    mut track_config: ResMut<TrackConfig>,
+    track_handle: Res<TrackHandle>,
+    tracks: Res<Assets<TrackAsset>>,
+) {
+    let Some(track_asset) = tracks.get(&track_handle.0) else {
+        return;
+    };
+    *track_config = TrackConfig::default();
 
-    let vertices: Vec<Point3<Real>> = positions
+    let samples = track_asset.samples();
+    let vertices: Vec<Point3<Real>> = samples
         .iter()
-        .map(|pos| Point3::new(pos.0, pos.1, pos.2))
+        .map(|s| Point3::new(s.position.x, s.position.y, s.position.z))
         .collect();
 
     let polyline = Polyline::new(vertices.clone(), None);
-    let initial_point = Point3::from(Vec3::ZERO);
+    let initial_point = Point3::from(Vec3::from(track_asset.start));
     let point_location = polyline.project_local_point_and_get_location(&initial_point, true);
     let (segment_i, segment_location) = point_location.1;
     let segment = polyline.segment(segment_i);
     track_config.polyline = Some(polyline.clone());
+    track_config.closed = !track_asset.open;
+    let track = Track::new(track_asset);
+    track_config.normals = track.normals;
+    track_config.left = track.left;
+    track_config.right = track.right;
+    track_config.tangents = samples.iter().map(|s| s.tangent).collect();
+    track_config.curvatures = samples.iter().map(|s| s.curvature).collect();
     track_config.start_segment_i = segment_i as usize;
 
     match segment_location {
@@ -42,14 +60,51 @@ pub fn track_polyline_start_system(mut cmd: Commands, mut track_config: ResMut<T
         track_config.segments[track_config.start_segment_i] + track_config.start_segment_shift;
     track_config.start_shift = start_shift;
     track_config.track_length = track_length;
+    track_config.finish_shift = if track_asset.open {
+        project_on_polyline(
+            &polyline,
+            &track_config.segments,
+            track_asset.finish_point(),
+        )
+        .distance
+    } else {
+        start_shift + track_length
+    };
+    track_config.sectors = match track_asset.sectors.is_empty() {
+        true => vec![track_length / 3., track_length * 2. / 3.],
+        false => {
+            let mut sectors: Vec<f32> = track_asset
+                .sectors
+                .iter()
+                .map(|p| {
+                    let distance =
+                        project_on_polyline(&polyline, &track_config.segments, Vec3::from(*p))
+                            .distance;
+                    (distance - start_shift).rem_euclid(track_length)
+                })
+                .collect();
+            sectors.sort_by(f32::total_cmp);
+            sectors
+        }
+    };
+    track_config.branches = track_asset
+        .branches
+        .iter()
+        .map(|branch| {
+            TrackBranchConfig::new(track_asset, branch, &polyline, &track_config.segments)
+        })
+        .collect();
+
+    track_config.surfaces = track_asset.surfaces.clone();
 
     println!(
-        "track length: {track_length:.1}, start_shift: {:.1}, segment_shift: {:.1}, segment_i: {}",
-        start_shift, track_config.start_segment_shift, track_config.start_segment_i
+        "track {}, length: {track_length:.1}, start_shift: {:.1}, segment_shift: {:.1}, segment_i: {}",
+        track_asset.name, start_shift, track_config.start_segment_shift, track_config.start_segment_i
     );
 
     cmd.spawn((
         Name::new("Track polyline"),
+        TrackEntity,
         Collider::from(ColliderShape::polyline(vertices, None)),
         RigidBody::Fixed,
         Sensor,
@@ -60,76 +115,126 @@ pub fn track_polyline_start_system(mut cmd: Commands, mut track_config: ResMut<T
 
 pub fn progress_system(
     track_config: Res<TrackConfig>,
+    racing_line: Res<RacingLine>,
     mut cars: Query<(&Transform, &mut CarTrack, Entity)>,
     car_res: Res<CarRes>,
     mut gizmos: Gizmos,
+    time: Res<Time>,
+    mut stage_events: MessageWriter<StageFinished>,
 ) {
-    let polyline = track_config.polyline.as_ref().unwrap();
-    let mut board: Vec<(Entity, f32)> = Vec::new();
+    if track_config.polyline.is_none() {
+        return;
+    }
+    let mut board: Vec<(f32, Mut<CarTrack>)> = Vec::with_capacity(cars.iter().len());
     for (tr, mut car, e) in cars.iter_mut() {
-        let point: Point3<Real> = Point3::from(tr.translation);
-        let point_location = polyline.project_local_point_and_get_location(&point, true);
-        let (segment_i, segment_location) = point_location.1;
-        let segment = polyline.segment(segment_i);
-        let segment_progress = match segment_location {
-            SegmentPointLocation::OnEdge(uv) => uv[1] * segment.length(),
-            _ => {
+        // cars move a few meters per frame, the search starts where the car was last time
+        let Some(projection) = track_config.project_near(tr.translation, car.segment) else {
+            continue;
+        };
+        car.segment = Some(projection.segment);
+        let mut dir = projection.direction;
+        let mut line_pos = projection.position;
+        let mut main_distance = projection.distance;
+
+        // a car closer to a branch than to the main line is on that branch
+        let mut offset = tr.translation.distance(line_pos);
+        car.branch = None;
+        car.speed_limit = None;
+        car.branch_segments
+            .resize(track_config.branches.len(), None);
+        for (branch_i, branch) in track_config.branches.iter().enumerate() {
+            // only branches running along this part of the main line can be closer
+            if !branch.spans(projection.distance, track_config.track_length) {
                 continue;
             }
-        };
+            let projection = branch.project_near(tr.translation, car.branch_segments[branch_i]);
+            car.branch_segments[branch_i] = Some(projection.segment);
+            if projection.offset < offset {
+                offset = projection.offset;
+                main_distance =
+                    branch.main_distance(projection.distance, track_config.track_length);
+                dir = projection.direction;
+                line_pos = projection.position;
+                car.branch = Some(branch_i);
+                car.speed_limit = branch.speed_limit_at(projection.distance);
+            }
+        }
 
-        let segments_progress: f32 =
-            track_config.segments[segment_i as usize] + segment_progress - track_config.start_shift;
-        let track_position: f32 = if segments_progress > 0. {
-            segments_progress
+        let segments_progress: f32 = main_distance - track_config.start_shift;
+        if !track_config.closed {
+            // point-to-point: no wrap around, timed from the start to the finish line
+            car.track_position = segments_progress;
+            car.ride_distance = segments_progress - car.start_shift;
+            if !car.finished && segments_progress >= track_config.stage_length() {
+                car.finished = true;
+                debug!("car {} finished in {:.2}s", car.index, car.stage_time);
+                stage_events.write(StageFinished {
+                    car: e,
+                    index: car.index,
+                    time: car.stage_time,
+                });
+            } else if !car.finished && segments_progress > 0. {
+                car.stage_time += time.delta_secs();
+            }
         } else {
-            segments_progress + track_config.track_length
-        };
+            let track_position: f32 = if segments_progress > 0. {
+                segments_progress
+            } else {
+                segments_progress + track_config.track_length
+            };
 
-        let mut ride_distance = if track_position >= car.start_shift {
-            track_position - car.start_shift
-        } else {
-            track_config.track_length + track_position - car.start_shift
-        };
-        let half = track_config.track_length / 2.;
-        if ride_distance - car.ride_distance > half {
-            // prevent increasing distance by going backward
-            ride_distance = ride_distance - track_config.track_length;
-        }
-        if ride_distance.is_sign_positive() && car.ride_distance.is_sign_negative()
-            || ride_distance < half && car.ride_distance > half
-        {
-            car.lap += 1;
-        }
-        if ride_distance.is_sign_negative() && car.ride_distance.is_sign_positive()
-            || ride_distance > -half && car.ride_distance < -half
-        {
-            car.lap -= 1;
+            let mut ride_distance = if track_position >= car.start_shift {
+                track_position - car.start_shift
+            } else {
+                track_config.track_length + track_position - car.start_shift
+            };
+            let half = track_config.track_length / 2.;
+            if ride_distance - car.ride_distance > half {
+                // prevent increasing distance by going backward
+                ride_distance = ride_distance - track_config.track_length;
+            }
+            if ride_distance.is_sign_positive() && car.ride_distance.is_sign_negative()
+                || ride_distance < half && car.ride_distance > half
+            {
+                car.lap += 1;
+            }
+            if ride_distance.is_sign_negative() && car.ride_distance.is_sign_positive()
+                || ride_distance > -half && car.ride_distance < -half
+            {
+                car.lap -= 1;
+            }
+            car.track_position = track_position;
+            car.ride_distance = ride_distance;
         }
-        car.track_position = track_position;
-        car.ride_distance = ride_distance;
 
-        let dir = Vec3::from(segment.direction().unwrap());
         car.line_dir = dir;
-        car.line_pos = Vec3::from(segment.a) + dir * segment_progress;
+        car.line_pos = line_pos;
+        // track points are the polyline vertices, the line is looked up by the same segment
+        let racing = match car.branch {
+            None => racing_line
+                .position_at(projection.segment, projection.t)
+                .zip(racing_line.direction_at(projection.segment))
+                .zip(racing_line.speed_at(projection.segment, projection.t)),
+            Some(_) => None,
+        };
+        (car.racing_pos, car.racing_dir, car.racing_speed) = match racing {
+            Some(((pos, dir), speed)) => (pos, dir, Some(speed)),
+            None => (line_pos, dir, None),
+        };
         if car_res.show_rays {
             let h = Vec3::Y * 0.6;
             gizmos.line(
                 h + tr.translation,
-                h + car.line_pos + Vec3::Y * tr.translation.y,
+                h + car.line_pos,
                 Color::srgba(0.5, 0.5, 0.5, 0.5),
             );
         }
-        board.push((e, track_position));
+        board.push((car.track_position, car));
     }
-    board.sort_by(|a, b| {
-        if a.1 > b.1 {
-            return Ordering::Greater;
+    board.sort_by(|a, b| a.0.total_cmp(&b.0));
+    for (i, (_, car)) in board.iter_mut().enumerate() {
+        if car.place != i {
+            car.place = i;
         }
-        Ordering::Less
-    });
-    for (i, (e, _)) in board.iter().enumerate() {
-        let (_, mut p, _) = cars.get_mut(*e).unwrap();
-        p.place = i;
     }
 }
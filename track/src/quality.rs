@@ -1,8 +1,7 @@
-use crate::{
-    AsphaltCell, ExtendedMaterialAsphalt, ExtendedMaterialGround, GroundCell, MaterialHandle,
-};
+use crate::{ExtendedMaterialAsphalt, ExtendedMaterialGround, MaterialHandle};
 use bevy::pbr::MeshMaterial3d;
 use bevy::prelude::*;
+use std::collections::HashMap;
 
 #[cfg(any(target_os = "ios", target_os = "android"))]
 const VISIBILITY: f32 = 200.;
@@ -11,97 +10,184 @@ const VISIBILITY: f32 = 400.;
 #[cfg(not(any(target_arch = "wasm32", target_os = "ios", target_os = "android")))]
 const VISIBILITY: f32 = 750.;
 
-const VISIBILITY_COLOR: f32 = VISIBILITY * 0.4;
+/// Side of the square buckets cells are sorted into.
+const CELL_GRID_SIZE: f32 = 100.;
 
+/// Distances from the camera, changeable while the game runs.
+#[derive(Resource, Debug, Clone)]
+pub struct CullingSettings {
+    /// Cells further away switch to the cheap material and mesh.
+    pub far: f32,
+    /// Cells further away are hidden.
+    pub visibility: f32,
+}
+
+impl Default for CullingSettings {
+    fn default() -> Self {
+        Self {
+            far: VISIBILITY * 0.4,
+            visibility: VISIBILITY,
+        }
+    }
+}
+
+impl CullingSettings {
+    pub fn band(&self, distance: f32) -> CullBand {
+        match distance {
+            d if d > self.visibility => CullBand::Hidden,
+            d if d > self.far => CullBand::Far,
+            _ => CullBand::Near,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CullBand {
+    #[default]
+    Near,
+    Far,
+    Hidden,
+}
+
+/// Entity shown or hidden by its distance from the camera.
+#[derive(Component, Debug, Default)]
+pub struct CullCell {
+    pub band: CullBand,
+}
+
+/// Detailed and simplified mesh of a cell, swapped with its distance band.
+#[derive(Component, Debug)]
+pub struct CellLod {
+    pub near: Handle<Mesh>,
+    pub far: Handle<Mesh>,
+}
+
+/// Cells sorted into square buckets, whole buckets are skipped while their band can't change.
+#[derive(Resource, Debug, Default)]
+pub struct CellGrid {
+    buckets: HashMap<IVec2, Vec<Entity>>,
+    entities: HashMap<Entity, IVec2>,
+    /// Camera position the bands were last updated for.
+    camera: Option<Vec2>,
+}
+
+impl CellGrid {
+    fn key(p: Vec2) -> IVec2 {
+        (p / CELL_GRID_SIZE).floor().as_ivec2()
+    }
+    fn insert(&mut self, entity: Entity, p: Vec2) {
+        let key = Self::key(p);
+        self.buckets.entry(key).or_default().push(entity);
+        self.entities.insert(entity, key);
+        // new cells start near, every bucket needs a look
+        self.camera = None;
+    }
+    fn remove(&mut self, entity: Entity) {
+        let Some(key) = self.entities.remove(&entity) else {
+            return;
+        };
+        if let Some(bucket) = self.buckets.get_mut(&key) {
+            bucket.retain(|e| *e != entity);
+        }
+    }
+    /// Band of every cell in the bucket if they all share one.
+    fn bucket_band(key: IVec2, camera: Vec2, settings: &CullingSettings) -> Option<CullBand> {
+        let min = key.as_vec2() * CELL_GRID_SIZE;
+        let max = min + Vec2::splat(CELL_GRID_SIZE);
+        let nearest = camera.clamp(min, max).distance(camera);
+        let farthest = (camera - min).abs().max((camera - max).abs()).length();
+        let band = settings.band(nearest);
+        (band == settings.band(farthest)).then_some(band)
+    }
+}
+
+pub fn cell_grid_system(
+    mut grid: ResMut<CellGrid>,
+    added: Query<(Entity, &Transform), Added<CullCell>>,
+    mut removed: RemovedComponents<CullCell>,
+) {
+    for entity in removed.read() {
+        grid.remove(entity);
+    }
+    for (entity, transform) in added.iter() {
+        grid.insert(entity, transform.translation.xz());
+    }
+}
+
+/// Updates the distance bands of cells in buckets the camera moved across a band boundary of.
 pub fn far_culling(
-    mut cmd: Commands,
-    handled_materials: Res<MaterialHandle>,
-    mut pset: ParamSet<(
-        Query<&Transform, With<Camera>>,
-        Query<
-            (
-                &Transform,
-                &mut Visibility,
-                &InheritedVisibility,
-                Entity,
-                &mut GroundCell,
-            ),
-            With<GroundCell>,
-        >,
-        Query<
-            (
-                &Transform,
-                &mut Visibility,
-                &InheritedVisibility,
-                Entity,
-                &mut AsphaltCell,
-            ),
-            With<AsphaltCell>,
-        >,
-    )>,
+    settings: Res<CullingSettings>,
+    mut grid: ResMut<CellGrid>,
+    cameras: Query<&Transform, With<Camera>>,
+    mut cells: Query<(&Transform, &mut CullCell, &mut Visibility)>,
 ) {
-    let cam_translation = if let Ok(cam_transform) = pset.p0().single() {
-        cam_transform.translation
-    } else {
+    let Ok(camera) = cameras.single() else {
         return;
     };
-
-    for (transform, mut cell_visibility, inherited_visibility, entity, mut cell) in
-        pset.p1().iter_mut()
-    {
-        let distance = (cam_translation - transform.translation).length();
-        if distance > VISIBILITY_COLOR {
-            if !cell.is_color {
-                cmd.entity(entity)
-                    .remove::<MeshMaterial3d<ExtendedMaterialGround>>();
-                cmd.entity(entity)
-                    .insert(MeshMaterial3d(handled_materials.ground_color.clone()));
-                cell.is_color = true;
-            }
-        } else {
-            if cell.is_color {
-                cmd.entity(entity)
-                    .remove::<MeshMaterial3d<StandardMaterial>>();
-                cmd.entity(entity)
-                    .insert(MeshMaterial3d(handled_materials.ground.clone()));
-                cell.is_color = false;
+    let camera = camera.translation.xz();
+    let previous = match settings.is_changed() {
+        true => None,
+        false => grid.camera,
+    };
+    if previous == Some(camera) {
+        return;
+    }
+    for (key, entities) in grid.buckets.iter() {
+        if let Some(previous) = previous {
+            let before = CellGrid::bucket_band(*key, previous, &settings);
+            if before.is_some() && before == CellGrid::bucket_band(*key, camera, &settings) {
+                continue;
             }
         }
-        if distance > VISIBILITY {
-            if inherited_visibility.get() {
-                *cell_visibility = Visibility::Hidden;
+        for entity in entities {
+            let Ok((transform, mut cell, mut visibility)) = cells.get_mut(*entity) else {
+                continue;
+            };
+            let band = settings.band(transform.translation.xz().distance(camera));
+            if cell.band == band {
+                continue;
             }
-        } else {
-            *cell_visibility = Visibility::Inherited;
+            cell.band = band;
+            *visibility = match band {
+                CullBand::Hidden => Visibility::Hidden,
+                CullBand::Near | CullBand::Far => Visibility::Inherited,
+            };
         }
     }
-    for (transform, mut cell_visibility, inherited_visibility, entity, mut cell) in
-        pset.p2().iter_mut()
-    {
-        let distance = (cam_translation - transform.translation).length();
-        if distance > VISIBILITY_COLOR {
-            if !cell.is_color {
-                cmd.entity(entity)
-                    .remove::<MeshMaterial3d<ExtendedMaterialAsphalt>>();
-                cmd.entity(entity)
-                    .insert(MeshMaterial3d(handled_materials.asphalt_color.clone()));
-                cell.is_color = true;
-            }
-        } else {
-            if cell.is_color {
-                cmd.entity(entity)
-                    .remove::<MeshMaterial3d<StandardMaterial>>();
-                cmd.entity(entity)
-                    .insert(MeshMaterial3d(handled_materials.asphalt.clone()));
-                cell.is_color = false;
-            }
-        }
-        if distance > VISIBILITY {
-            if inherited_visibility.get() {
-                *cell_visibility = Visibility::Hidden;
-            }
-        } else {
-            *cell_visibility = Visibility::Inherited;
+    grid.camera = Some(camera);
+}
+
+pub fn cell_lod_system(mut cells: Query<(&CullCell, &CellLod, &mut Mesh3d), Changed<CullCell>>) {
+    for (cell, lod, mut mesh) in cells.iter_mut() {
+        let handle = match cell.band {
+            CullBand::Near => &lod.near,
+            CullBand::Far | CullBand::Hidden => &lod.far,
+        };
+        if mesh.0 != *handle {
+            mesh.0 = handle.clone();
         }
     }
 }
+
+/// Far cells keep their material type and only swap to the handle without shader detail.
+pub fn cell_material_system(
+    handled_materials: Res<MaterialHandle>,
+    mut grounds: Query<(&CullCell, &mut MeshMaterial3d<ExtendedMaterialGround>), Changed<CullCell>>,
+    mut asphalts: Query<
+        (&CullCell, &mut MeshMaterial3d<ExtendedMaterialAsphalt>),
+        Changed<CullCell>,
+    >,
+) {
+    for (cell, mut material) in grounds.iter_mut() {
+        material.0 = match cell.band {
+            CullBand::Near => handled_materials.ground.clone(),
+            CullBand::Far | CullBand::Hidden => handled_materials.ground_far.clone(),
+        };
+    }
+    for (cell, mut material) in asphalts.iter_mut() {
+        material.0 = match cell.band {
+            CullBand::Near => handled_materials.asphalt.clone(),
+            CullBand::Far | CullBand::Hidden => handled_materials.asphalt_far.clone(),
+        };
+    }
+}
@@ -0,0 +1,396 @@
+use crate::{Track, TrackAsset, TrackHandle};
+use bevy::platform::time::Instant;
+use bevy::prelude::*;
+use bevy::tasks::{futures::check_ready, AsyncComputeTaskPool, Task};
+use bevy_garage_car::CarRes;
+
+/// Points moved at once by the minimum time search, the shift fades out towards both ends.
+const MIN_TIME_SPAN: usize = 12;
+const MIN_TIME_PASSES: usize = 12;
+/// Track points on each side whose curvature bounds the inside offset.
+const CORNER_WINDOW: usize = 3;
+/// Spacing of the points relaxed together, from the whole corner down to single points.
+const RELAX_STRIDES: [usize; 6] = [32, 16, 8, 4, 2, 1];
+
+/// Solver parameters, the speed profile uses a point mass with one grip circle.
+#[derive(Resource, Debug, Clone)]
+pub struct RacingLineSettings {
+    /// Distance kept from the road edges in meters.
+    pub margin: f32,
+    /// Relaxation passes of the minimum curvature line at every point spacing.
+    pub iterations: usize,
+    /// Refines the minimum curvature line for the shortest lap time of the speed profile.
+    pub min_time: bool,
+    /// Lateral acceleration at the grip limit in m/s².
+    pub grip: f32,
+    pub acceleration: f32,
+    pub braking: f32,
+    /// Top speed in m/s.
+    pub max_speed: f32,
+}
+
+impl Default for RacingLineSettings {
+    fn default() -> Self {
+        Self {
+            margin: 1.,
+            iterations: 100,
+            min_time: false,
+            grip: 12.,
+            acceleration: 6.,
+            braking: 12.,
+            max_speed: 80.,
+        }
+    }
+}
+
+/// Line through every track point, offset from the centerline.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct RacingLine {
+    /// Lateral offset in meters, positive to the left of the driving direction.
+    pub offsets: Vec<f32>,
+    pub positions: Vec<Vec3>,
+    /// Signed horizontal curvature of the line in 1/m, positive when turning left.
+    pub curvatures: Vec<f32>,
+    /// Target speed in m/s.
+    pub speeds: Vec<f32>,
+    /// Distance along the line from the first point.
+    pub distances: Vec<f32>,
+    pub lap_time: f32,
+}
+
+impl RacingLine {
+    /// Position on the line between track point `i` and the next one.
+    pub fn position_at(&self, i: usize, t: f32) -> Option<Vec3> {
+        let a = self.positions.get(i)?;
+        Some(a.lerp(*self.positions.get(i + 1).unwrap_or(a), t))
+    }
+    /// Driving direction of the line between track point `i` and the next one.
+    pub fn direction_at(&self, i: usize) -> Option<Vec3> {
+        let (a, b) = match i + 1 < self.positions.len() {
+            true => (self.positions.get(i)?, self.positions.get(i + 1)?),
+            false => (
+                self.positions.get(i.checked_sub(1)?)?,
+                self.positions.get(i)?,
+            ),
+        };
+        (*b - *a).try_normalize()
+    }
+    pub fn speed_at(&self, i: usize, t: f32) -> Option<f32> {
+        let a = self.speeds.get(i)?;
+        Some(a + (self.speeds.get(i + 1).unwrap_or(a) - a) * t)
+    }
+}
+
+pub fn racing_line(track: &Track, settings: &RacingLineSettings) -> RacingLine {
+    let n = track.points.len();
+    if n < 3 {
+        return RacingLine::default();
+    }
+    // closed tracks repeat the first point last
+    let m = if track.closed { n - 1 } else { n };
+    // right and left bound of the offset, the inside of corners tighter than the road is wide
+    // folds over so the line keeps clear of their center
+    let limits: Vec<(f32, f32)> = (0..m)
+        .map(|i| {
+            let edge = (track.width[i] - settings.margin).max(0.);
+            (0..=2 * CORNER_WINDOW).fold((-edge, edge), |(right, left), k| {
+                let curvature = match track.closed {
+                    true => track.curvature[(i + m + k - CORNER_WINDOW) % m],
+                    false => (i + k)
+                        .checked_sub(CORNER_WINDOW)
+                        .and_then(|j| track.curvature.get(j).copied())
+                        .unwrap_or(0.),
+                };
+                match curvature {
+                    k if k > 0. => (right, left.min(0.5 / k)),
+                    k if k < 0. => (right.max(0.5 / k), left),
+                    _ => (right, left),
+                }
+            })
+        })
+        .collect();
+    let mut offsets = vec![0.; m];
+    for stride in RELAX_STRIDES {
+        for _ in 0..settings.iterations {
+            relax(track, &limits, &mut offsets, stride);
+        }
+    }
+    if settings.min_time {
+        offsets = min_time(track, settings, &limits, offsets);
+    }
+    let mut line = line_geometry(track, &offsets);
+    line.speeds = speed_profile(track.closed, &line.curvatures, &line.distances, settings);
+    line.lap_time = lap_time(&line.speeds, &line.distances);
+    line
+}
+
+fn line_point(track: &Track, i: usize, offset: f32) -> Vec3 {
+    track.points[i] + track.left_norm[i] * offset
+}
+
+/// Signed horizontal curvature of the circle through three points, positive when turning left.
+fn curvature(a: Vec3, b: Vec3, c: Vec3) -> f32 {
+    let (a, b, c) = (a.xz(), b.xz(), c.xz());
+    let length = a.distance(b) * b.distance(c) * a.distance(c);
+    match length > 0. {
+        // left of +Z is +X, which is a clockwise turn seen from above
+        true => -2. * (b - a).perp_dot(c - b) / length,
+        false => 0.,
+    }
+}
+
+/// Moves every `stride`-th point so its curvature is the average of its neighbours',
+/// the points in between follow linearly.
+fn relax(track: &Track, limits: &[(f32, f32)], offsets: &mut [f32], stride: usize) {
+    let m = offsets.len();
+    let anchor = |k: isize| match track.closed {
+        true => Some((k * stride as isize).rem_euclid(m as isize) as usize),
+        false => usize::try_from(k * stride as isize).ok().filter(|j| *j < m),
+    };
+    let anchors = m.div_ceil(stride) as isize;
+    for k in 0..anchors {
+        let [Some(a), Some(b), Some(i), Some(c), Some(d)] =
+            [k - 2, k - 1, k, k + 1, k + 2].map(anchor)
+        else {
+            // ends of open tracks stay on the centerline
+            continue;
+        };
+        let [a, b, p, c, d] = [a, b, i, c, d].map(|j| line_point(track, j, offsets[j]));
+        let (to_b, to_c) = (b.distance(p), c.distance(p));
+        let target = (to_c * curvature(a, b, p) + to_b * curvature(p, c, d))
+            / (to_b + to_c).max(f32::EPSILON);
+        // curvature is close to linear in the offset over a small move
+        let delta = 0.01;
+        let k0 = curvature(b, p, c);
+        let k1 = curvature(b, line_point(track, i, offsets[i] + delta), c);
+        if (k1 - k0).abs() > f32::EPSILON {
+            let offset = offsets[i] + (target - k0) * delta / (k1 - k0);
+            offsets[i] = offset.clamp(limits[i].0, limits[i].1);
+        }
+    }
+    if stride > 1 {
+        for k in 0..anchors {
+            let (Some(from), Some(to)) = (anchor(k), anchor(k + 1)) else {
+                continue;
+            };
+            let span = match to > from {
+                true => to - from,
+                false => m - from + to,
+            };
+            for s in 1..span {
+                let j = (from + s) % m;
+                let t = s as f32 / span as f32;
+                offsets[j] = (offsets[from] + (offsets[to] - offsets[from]) * t)
+                    .clamp(limits[j].0, limits[j].1);
+            }
+        }
+    }
+}
+
+/// Pattern search on the lap time, shifting a span of points at a time.
+fn min_time(
+    track: &Track,
+    settings: &RacingLineSettings,
+    limits: &[(f32, f32)],
+    mut offsets: Vec<f32>,
+) -> Vec<f32> {
+    let m = offsets.len();
+    let time = |offsets: &[f32]| {
+        let line = line_geometry(track, offsets);
+        lap_time(
+            &speed_profile(track.closed, &line.curvatures, &line.distances, settings),
+            &line.distances,
+        )
+    };
+    let mut best = time(&offsets);
+    let mut step = limits.iter().fold(0., |max: f32, (_, left)| max.max(*left)) / 4.;
+    for _ in 0..MIN_TIME_PASSES {
+        let mut improved = false;
+        for center in (0..m).step_by(MIN_TIME_SPAN / 2) {
+            for direction in [1., -1.] {
+                let mut trial = offsets.clone();
+                for k in 0..=2 * MIN_TIME_SPAN {
+                    let j = center as isize + k as isize - MIN_TIME_SPAN as isize;
+                    let j = match track.closed {
+                        true => j.rem_euclid(m as isize) as usize,
+                        false if (2..m as isize - 2).contains(&j) => j as usize,
+                        false => continue,
+                    };
+                    let fade = (k as f32 / MIN_TIME_SPAN as f32 * std::f32::consts::PI).sin();
+                    trial[j] = (trial[j] + direction * step * fade).clamp(limits[j].0, limits[j].1);
+                }
+                let trial_time = time(&trial);
+                if trial_time < best {
+                    best = trial_time;
+                    offsets = trial;
+                    improved = true;
+                    break;
+                }
+            }
+        }
+        if !improved {
+            step /= 2.;
+        }
+    }
+    offsets
+}
+
+fn line_geometry(track: &Track, offsets: &[f32]) -> RacingLine {
+    let n = track.points.len();
+    let m = offsets.len();
+    let offsets: Vec<f32> = (0..n).map(|i| offsets[i % m]).collect();
+    let positions: Vec<Vec3> = (0..n).map(|i| line_point(track, i, offsets[i])).collect();
+    let mut distances: Vec<f32> = vec![0.];
+    for w in positions.windows(2) {
+        distances.push(distances.last().unwrap() + w[0].distance(w[1]));
+    }
+    let curvatures: Vec<f32> = (0..n)
+        .map(|i| {
+            let (a, c) = match (track.closed, i) {
+                (true, 0) => (m - 1, 1),
+                (true, i) if i + 1 == n => (m - 1, 1),
+                (false, 0) => return 0.,
+                (false, i) if i + 1 == n => return 0.,
+                (_, i) => (i - 1, i + 1),
+            };
+            curvature(positions[a], positions[i], positions[c])
+        })
+        .collect();
+    RacingLine {
+        offsets,
+        positions,
+        curvatures,
+        distances,
+        ..default()
+    }
+}
+
+/// Fastest speeds a point mass can hold through the line, from the grip circle.
+pub fn speed_profile(
+    closed: bool,
+    curvatures: &[f32],
+    distances: &[f32],
+    settings: &RacingLineSettings,
+) -> Vec<f32> {
+    let n = curvatures.len();
+    let limit = |k: f32| match k.abs() > 0. {
+        true => (settings.grip / k.abs()).sqrt().min(settings.max_speed),
+        false => settings.max_speed,
+    };
+    let mut speeds: Vec<f32> = curvatures.iter().map(|k| limit(*k)).collect();
+    // grip left for accelerating or braking after cornering
+    let longitudinal = |v: f32, k: f32, max: f32| {
+        let lateral = (v * v * k.abs() / settings.grip).min(1.);
+        max * (1. - lateral * lateral).sqrt()
+    };
+    // closed tracks repeat the first point last
+    let m = if closed { n - 1 } else { n };
+    let (first, steps) = match closed {
+        // the slowest corner can't get any slower, both passes go a full lap from there
+        true => (
+            (0..m)
+                .min_by(|a, b| speeds[*a].total_cmp(&speeds[*b]))
+                .unwrap_or(0),
+            m,
+        ),
+        false => {
+            speeds[0] = 0.;
+            (0, m - 1)
+        }
+    };
+    let ds = |a: usize, b: usize| match b > a {
+        true => distances[b] - distances[a],
+        false => distances[n - 1] - distances[a] + distances[b],
+    };
+    for s in 0..steps {
+        let (a, b) = ((first + s) % m, (first + s + 1) % m);
+        let a_max = longitudinal(speeds[a], curvatures[a], settings.acceleration);
+        speeds[b] = speeds[b].min((speeds[a] * speeds[a] + 2. * a_max * ds(a, b)).sqrt());
+    }
+    let last = match closed {
+        true => first + m,
+        false => m - 1,
+    };
+    for s in 0..steps {
+        let (a, b) = ((last - s - 1) % m, (last - s) % m);
+        let b_max = longitudinal(speeds[b], curvatures[b], settings.braking);
+        speeds[a] = speeds[a].min((speeds[b] * speeds[b] + 2. * b_max * ds(a, b)).sqrt());
+    }
+    if closed {
+        speeds[n - 1] = speeds[0];
+    }
+    speeds
+}
+
+fn lap_time(speeds: &[f32], distances: &[f32]) -> f32 {
+    (1..speeds.len())
+        .map(|i| 2. * (distances[i] - distances[i - 1]) / (speeds[i - 1] + speeds[i]).max(0.1))
+        .sum()
+}
+
+/// Line being solved off the main thread for the track that was loaded last.
+#[derive(Resource, Default)]
+pub struct RacingLineTask(Option<Task<RacingLine>>);
+
+pub fn racing_line_start_system(
+    mut racing_line_res: ResMut<RacingLine>,
+    mut racing_line_task: ResMut<RacingLineTask>,
+    settings: Res<RacingLineSettings>,
+    track_handle: Res<TrackHandle>,
+    tracks: Res<Assets<TrackAsset>>,
+) {
+    let Some(track_asset) = tracks.get(&track_handle.0) else {
+        return;
+    };
+    // the line of the previous track is gone, a task still solving it is dropped and cancelled
+    *racing_line_res = RacingLine::default();
+    let track = Track::new(track_asset);
+    let settings = settings.clone();
+    let name = track_asset.name.clone();
+    racing_line_task.0 = Some(AsyncComputeTaskPool::get().spawn(async move {
+        let start = Instant::now();
+        let line = racing_line(&track, &settings);
+        println!(
+            "track {name} racing line, lap time: {:.2}s, solved in {:.0}ms",
+            line.lap_time,
+            start.elapsed().as_secs_f32() * 1000.
+        );
+        line
+    }));
+}
+
+pub fn racing_line_task_system(
+    mut racing_line_res: ResMut<RacingLine>,
+    mut racing_line_task: ResMut<RacingLineTask>,
+) {
+    let Some(task) = racing_line_task.0.as_mut() else {
+        return;
+    };
+    if let Some(line) = check_ready(task) {
+        *racing_line_res = line;
+        racing_line_task.0 = None;
+    }
+}
+
+/// Line colored from red in the slowest corners to green at top speed.
+pub fn racing_line_gizmo_system(
+    racing_line: Res<RacingLine>,
+    settings: Res<RacingLineSettings>,
+    car_res: Res<CarRes>,
+    mut gizmos: Gizmos,
+) {
+    if !car_res.show_rays {
+        return;
+    }
+    let h = Vec3::Y * 0.1;
+    gizmos.linestrip_gradient(
+        racing_line
+            .positions
+            .iter()
+            .zip(racing_line.speeds.iter())
+            .map(|(p, v)| {
+                let f = (v / settings.max_speed).clamp(0., 1.);
+                (*p + h, Color::srgb(1. - f, f, 0.))
+            }),
+    );
+}
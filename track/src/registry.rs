@@ -0,0 +1,205 @@
+use crate::{
+    CarTrack, SpawnCarOnTrackEvent, SpawnPosition, TrackAsset, TrackEntity, TrackGenerator,
+    TrackHandle, TrackLayoutError, TrackLoadedEvent, DEFAULT_TRACK_PATH,
+};
+use bevy::prelude::*;
+use bevy::tasks::{futures::check_ready, AsyncComputeTaskPool, Task};
+use bevy_garage_car::{CarWheels, Player};
+
+#[derive(Debug, Clone)]
+pub struct TrackEntry {
+    pub name: String,
+    pub path: String,
+    /// Track made in code, such as a generated circuit, loaded instead of the path.
+    pub asset: Option<TrackAsset>,
+    /// Makes the asset the first time the track is loaded.
+    pub generator: Option<TrackGenerator>,
+}
+
+impl TrackEntry {
+    pub fn new(name: &str, path: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            path: path.to_string(),
+            asset: None,
+            generator: None,
+        }
+    }
+    /// Generated track whose asset hasn't been made yet.
+    pub fn needs_generating(&self) -> bool {
+        self.asset.is_none() && self.generator.is_some()
+    }
+    pub fn generated(generator: TrackGenerator) -> Self {
+        Self {
+            name: generator.name(),
+            path: String::new(),
+            asset: None,
+            generator: Some(generator),
+        }
+    }
+}
+
+#[derive(Resource, Debug)]
+pub struct TrackRegistry {
+    pub tracks: Vec<TrackEntry>,
+    pub current: usize,
+}
+
+impl Default for TrackRegistry {
+    fn default() -> Self {
+        let tracks = vec![
+            TrackEntry::new("default", DEFAULT_TRACK_PATH),
+            TrackEntry::new("oval", "tracks/oval.track.ron"),
+            TrackEntry::new("hillclimb", "tracks/hillclimb.track.ron"),
+            TrackEntry::generated(TrackGenerator::new(1)),
+        ];
+        Self { tracks, current: 0 }
+    }
+}
+
+impl TrackRegistry {
+    pub fn find(&self, name: &str) -> Option<usize> {
+        self.tracks.iter().position(|t| t.name == name)
+    }
+    pub fn current(&self) -> &TrackEntry {
+        &self.tracks[self.current]
+    }
+    pub fn next(&self) -> &TrackEntry {
+        &self.tracks[(self.current + 1) % self.tracks.len()]
+    }
+}
+
+#[derive(Debug, Message)]
+pub struct LoadTrack {
+    pub name: String,
+}
+
+#[derive(Resource, Default, Debug)]
+pub struct TrackCarsRespawn {
+    pub cars: Vec<(bool, usize)>,
+}
+
+pub fn load_track_system(
+    mut events: MessageReader<LoadTrack>,
+    mut cmd: Commands,
+    asset_server: Res<AssetServer>,
+    mut registry: ResMut<TrackRegistry>,
+    mut track_handle: ResMut<TrackHandle>,
+    track_entities: Query<Entity, With<TrackEntity>>,
+    mut loaded_events: MessageWriter<TrackLoadedEvent>,
+) {
+    let Some(event) = events.read().last() else {
+        return;
+    };
+    let Some(track_i) = registry.find(&event.name) else {
+        println!("track {} is not registered", event.name);
+        return;
+    };
+    if registry.tracks[track_i].needs_generating() {
+        // loaded again once the generator task has made the asset
+        return;
+    }
+
+    for e in track_entities.iter() {
+        cmd.entity(e).despawn();
+    }
+    registry.current = track_i;
+    let entry = registry.current();
+    track_handle.0 = match &entry.asset {
+        Some(asset) => asset_server.add(asset.clone()),
+        None => asset_server.load(&entry.path),
+    };
+    if asset_server.is_loaded_with_dependencies(&track_handle.0) {
+        loaded_events.write(TrackLoadedEvent);
+    }
+}
+
+/// Layout search of a generated track being picked, with its name.
+#[derive(Resource, Default)]
+pub struct TrackGeneratorTask(Option<(String, Task<Result<TrackAsset, TrackLayoutError>>)>);
+
+/// Starts the layout search of a generated track the first time it is picked, it takes a while.
+pub fn track_generator_start_system(
+    mut events: MessageReader<LoadTrack>,
+    registry: Res<TrackRegistry>,
+    mut generator_task: ResMut<TrackGeneratorTask>,
+) {
+    let Some(event) = events.read().last() else {
+        return;
+    };
+    let Some(entry) = registry.find(&event.name).map(|i| &registry.tracks[i]) else {
+        return;
+    };
+    let generating = generator_task
+        .0
+        .as_ref()
+        .is_some_and(|(name, _)| *name == entry.name);
+    if !entry.needs_generating() || generating {
+        return;
+    }
+    let Some(generator) = entry.generator.clone() else {
+        return;
+    };
+    let task = AsyncComputeTaskPool::get().spawn(async move { generator.generate() });
+    generator_task.0 = Some((entry.name.clone(), task));
+}
+
+/// Keeps the generated asset in the registry and loads the track.
+pub fn track_generator_task_system(
+    mut registry: ResMut<TrackRegistry>,
+    mut generator_task: ResMut<TrackGeneratorTask>,
+    mut load_events: MessageWriter<LoadTrack>,
+) {
+    let Some((name, task)) = generator_task.0.as_mut() else {
+        return;
+    };
+    let Some(result) = check_ready(task) else {
+        return;
+    };
+    let name = std::mem::take(name);
+    generator_task.0 = None;
+    match result {
+        Ok(asset) => {
+            if let Some(i) = registry.find(&name) {
+                registry.tracks[i].asset = Some(asset);
+                load_events.write(LoadTrack { name });
+            }
+        }
+        Err(e) => warn!("track {name}: {e}"),
+    }
+}
+
+pub fn track_cars_despawn_system(
+    mut events: MessageReader<LoadTrack>,
+    mut cmd: Commands,
+    registry: Res<TrackRegistry>,
+    mut respawn: ResMut<TrackCarsRespawn>,
+    mut cars: Query<(Entity, &CarTrack, &mut CarWheels, Has<Player>)>,
+) {
+    let Some(event) = events.read().last() else {
+        return;
+    };
+    // the track is current once load_track_system switched to it
+    if registry.current().name != event.name {
+        return;
+    }
+    for (e, car_track, mut wheels, player) in cars.iter_mut() {
+        cmd.entity(e).despawn();
+        wheels.despawn(&mut cmd);
+        respawn.cars.push((player, car_track.index));
+    }
+}
+
+pub fn track_cars_respawn_system(
+    mut respawn: ResMut<TrackCarsRespawn>,
+    mut spawn_events: MessageWriter<SpawnCarOnTrackEvent>,
+) {
+    for (slot, (player, index)) in respawn.cars.drain(..).enumerate() {
+        spawn_events.write(SpawnCarOnTrackEvent {
+            player,
+            index,
+            count: 1,
+            position: SpawnPosition::Grid(slot),
+        });
+    }
+}
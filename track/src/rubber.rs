@@ -0,0 +1,182 @@
+use crate::{contact_slip, Surface, WheelSurface};
+use crate::{ExtendedMaterialAsphalt, MaterialHandle, PolylineProjection, TrackConfig, Weather};
+use bevy::asset::RenderAssetUsages;
+use bevy::image::ImageSampler;
+use bevy::prelude::*;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use bevy_garage_car::Wheel;
+use bevy_rapier3d::prelude::*;
+
+/// Length of the road a row of the field covers, in meters.
+const RUBBER_CELL_LENGTH: f32 = 4.;
+/// Bins across the road from the right edge to the left.
+const RUBBER_LANES: usize = 16;
+/// Rubber laid per second for every m/s a tyre slips over the road.
+const RUBBER_LAYING: f32 = 0.15;
+/// Slip above this is a spin or a slide, it doesn't lay more rubber.
+const RUBBER_MAX_SLIP: f32 = 5.;
+/// Rubber washed away per second in full rain.
+const RUBBER_WASHING: f32 = 1. / 300.;
+/// Extra grip on a fully rubbered-in dry road.
+const RUBBER_GRIP: f32 = 0.1;
+/// Seconds between uploads of the field to the asphalt texture.
+const RUBBER_TEXTURE_INTERVAL: f32 = 1.;
+
+/// Rubber on the road along the track distance and across the road, grows where tyres slip.
+#[derive(Resource, Debug, Default)]
+pub struct GripField {
+    pub cells: usize,
+    /// From 0 clean to 1 rubbered in, the lanes of a cell next to each other.
+    pub rubber: Vec<f32>,
+    /// Field as a texture, lanes across and cells down, sampled by the asphalt shader.
+    pub texture: Handle<Image>,
+    changed: bool,
+}
+
+/// Cells covering a track, the last one reaching past its end.
+fn cells(track_length: f32) -> usize {
+    (track_length / RUBBER_CELL_LENGTH).ceil().max(1.) as usize
+}
+
+/// Texture row coordinate of a track distance, the texture is a whole number of cells long.
+pub fn rubber_texture_v(distance: f32, track_length: f32) -> f32 {
+    distance / (cells(track_length) as f32 * RUBBER_CELL_LENGTH)
+}
+
+impl GripField {
+    pub fn new(track_length: f32) -> Self {
+        let cells = cells(track_length);
+        Self {
+            cells,
+            rubber: vec![0.; cells * RUBBER_LANES],
+            ..default()
+        }
+    }
+    /// Cell row and lane position of a track distance and lateral position from 0 right to 1 left.
+    fn cell(&self, distance: f32, lateral: f32) -> (usize, f32) {
+        let cell = ((distance / RUBBER_CELL_LENGTH) as usize).min(self.cells - 1);
+        (cell, lateral * RUBBER_LANES as f32 - 0.5)
+    }
+    pub fn rubber_at(&self, distance: f32, lateral: f32) -> f32 {
+        if self.rubber.is_empty() {
+            return 0.;
+        }
+        let (cell, lane) = self.cell(distance, lateral);
+        let row = &self.rubber[cell * RUBBER_LANES..(cell + 1) * RUBBER_LANES];
+        let a = lane.floor();
+        let t = lane - a;
+        let at = |lane: f32| row[(lane.max(0.) as usize).min(RUBBER_LANES - 1)];
+        at(a) + (at(a + 1.) - at(a)) * t
+    }
+    /// Adds rubber, shared between the two closest lanes.
+    pub fn lay(&mut self, distance: f32, lateral: f32, amount: f32) {
+        if self.rubber.is_empty() || !(0. ..=1.).contains(&lateral) {
+            return;
+        }
+        let (cell, lane) = self.cell(distance, lateral);
+        let a = lane.floor();
+        let t = lane - a;
+        for (lane, share) in [(a, 1. - t), (a + 1., t)] {
+            if (0. ..RUBBER_LANES as f32).contains(&lane) {
+                let rubber = &mut self.rubber[cell * RUBBER_LANES + lane as usize];
+                *rubber = (*rubber + amount * share).min(1.);
+            }
+        }
+        self.changed = true;
+    }
+    fn texture_data(&self) -> Vec<u8> {
+        self.rubber.iter().map(|r| (r * 255.) as u8).collect()
+    }
+}
+
+/// Grip multiplier of the rubber on the road, water covers it.
+pub fn rubber_grip(rubber: f32, wetness: f32) -> f32 {
+    1. + RUBBER_GRIP * rubber * (1. - wetness)
+}
+
+/// Distance along the polyline and lateral position from 0 at the right edge to 1 at the left
+/// of a point projected on the centerline, none off the road.
+pub fn track_coordinates(
+    track_config: &TrackConfig,
+    projection: &PolylineProjection,
+    p: Vec3,
+) -> Option<(f32, f32)> {
+    let (lateral, width) = track_config.lateral_at(projection, p)?;
+    let lateral = lateral / width.max(f32::EPSILON);
+    (0. ..=1.)
+        .contains(&lateral)
+        .then_some((projection.distance, lateral))
+}
+
+pub fn rubber_start_system(
+    mut grip_field: ResMut<GripField>,
+    track_config: Res<TrackConfig>,
+    mut images: ResMut<Assets<Image>>,
+    handled_materials: Res<MaterialHandle>,
+    mut asphalt_materials: ResMut<Assets<ExtendedMaterialAsphalt>>,
+) {
+    // the field of the previous track goes with its texture
+    images.remove(&grip_field.texture);
+    *grip_field = GripField::new(track_config.track_length);
+    let mut image = Image::new(
+        Extent3d {
+            width: RUBBER_LANES as u32,
+            height: grip_field.cells as u32,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        grip_field.texture_data(),
+        TextureFormat::R8Unorm,
+        RenderAssetUsages::RENDER_WORLD,
+    );
+    image.sampler = ImageSampler::linear();
+    grip_field.texture = images.add(image);
+    for handle in [&handled_materials.asphalt, &handled_materials.asphalt_far] {
+        if let Some(asphalt) = asphalt_materials.get_mut(handle) {
+            asphalt.extension.rubber = Some(grip_field.texture.clone());
+        }
+    }
+}
+
+/// Lays rubber under slipping tyres on the asphalt, rain washes it off.
+pub fn rubber_system(
+    time: Res<Time>,
+    weather: Res<Weather>,
+    mut grip_field: ResMut<GripField>,
+    wheels: Query<(&Velocity, &Wheel, &WheelSurface)>,
+) {
+    let dt = time.delta_secs();
+    for (velocity, wheel, wheel_surface) in wheels.iter() {
+        if wheel_surface.surface != Some(Surface::Asphalt) {
+            continue;
+        }
+        let slip = contact_slip(velocity, wheel.radius).min(RUBBER_MAX_SLIP);
+        if let Some((distance, lateral)) = wheel_surface.coordinates {
+            grip_field.lay(distance, lateral, RUBBER_LAYING * slip * dt);
+        }
+    }
+    if weather.rain > 0. {
+        let washing = weather.rain * RUBBER_WASHING * dt;
+        for rubber in grip_field.rubber.iter_mut() {
+            *rubber = (*rubber - washing).max(0.);
+        }
+        grip_field.changed = true;
+    }
+}
+
+pub fn rubber_texture_system(
+    time: Res<Time>,
+    mut grip_field: ResMut<GripField>,
+    mut images: ResMut<Assets<Image>>,
+    mut elapsed: Local<f32>,
+) {
+    *elapsed += time.delta_secs();
+    if *elapsed < RUBBER_TEXTURE_INTERVAL || !grip_field.changed {
+        return;
+    }
+    *elapsed = 0.;
+    grip_field.changed = false;
+    if let Some(image) = images.get_mut(&grip_field.texture) {
+        image.data = Some(grip_field.texture_data());
+    }
+}
@@ -0,0 +1,198 @@
+use crate::{CullCell, TrackAsset, TrackConfig, TrackEntity};
+use bevy::prelude::*;
+use bevy_garage_car::STATIC_GROUP;
+use bevy_rapier3d::prelude::*;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum SceneryKind {
+    Grandstand,
+    MarshalPost,
+    TireStack,
+    Cone,
+    Sign,
+    Tree,
+}
+
+impl SceneryKind {
+    fn mesh(self) -> Mesh {
+        match self {
+            SceneryKind::Grandstand => Cuboid::new(8., 6., 20.).into(),
+            SceneryKind::MarshalPost => Cuboid::new(2., 3., 2.).into(),
+            SceneryKind::TireStack => Cylinder::new(0.35, 0.8).into(),
+            SceneryKind::Cone => Cone::new(0.15, 0.5).into(),
+            SceneryKind::Sign => Cuboid::new(3., 1.5, 0.1).into(),
+            SceneryKind::Tree => Cone::new(2., 8.).into(),
+        }
+    }
+    fn color(self) -> Color {
+        match self {
+            SceneryKind::Grandstand => Color::srgb(0.55, 0.55, 0.6),
+            SceneryKind::MarshalPost => Color::srgb(0.9, 0.45, 0.1),
+            SceneryKind::TireStack => Color::srgb(0.05, 0.05, 0.05),
+            SceneryKind::Cone => Color::srgb(1., 0.35, 0.),
+            SceneryKind::Sign => Color::srgb(0.95, 0.95, 0.95),
+            SceneryKind::Tree => Color::srgb(0.1, 0.35, 0.12),
+        }
+    }
+    /// Height of the mesh center above the ground.
+    fn lift(self) -> f32 {
+        match self {
+            SceneryKind::Grandstand => 3.,
+            SceneryKind::MarshalPost => 1.5,
+            SceneryKind::TireStack => 0.4,
+            SceneryKind::Cone => 0.25,
+            SceneryKind::Sign => 2.,
+            SceneryKind::Tree => 4.,
+        }
+    }
+    /// Collider for objects cars can hit, trees only block with their trunk.
+    fn collider(self) -> Option<Collider> {
+        match self {
+            SceneryKind::Grandstand => Some(Collider::cuboid(4., 3., 10.)),
+            SceneryKind::MarshalPost => Some(Collider::cuboid(1., 1.5, 1.)),
+            SceneryKind::TireStack => Some(Collider::cylinder(0.4, 0.35)),
+            SceneryKind::Cone => Some(Collider::cone(0.25, 0.15)),
+            SceneryKind::Sign => None,
+            SceneryKind::Tree => Some(Collider::cylinder(4., 0.3)),
+        }
+    }
+}
+
+/// Object placed by distance from the start line and offset from the centerline.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct SceneryItem {
+    pub kind: SceneryKind,
+    pub meters: f32,
+    /// Lateral offset in meters, positive to the left of the driving direction.
+    pub offset: f32,
+    /// Rotation in degrees from facing along the track.
+    #[serde(default)]
+    pub yaw: f32,
+}
+
+/// Objects scattered at random on both sides beyond the walls.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct ScatterRule {
+    pub kind: SceneryKind,
+    /// Objects per 100 m of track on each side.
+    pub density: f32,
+    /// Band beyond the wall the objects are placed in.
+    pub from_wall: f32,
+    pub depth: f32,
+    #[serde(default)]
+    pub seed: u64,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SceneryPlacement {
+    pub kind: SceneryKind,
+    pub transform: Transform,
+}
+
+/// Where the decoration layer of a track puts its objects.
+pub fn scenery_placements(
+    track_asset: &TrackAsset,
+    track_config: &TrackConfig,
+) -> Vec<SceneryPlacement> {
+    if track_config.polyline.is_none() {
+        return vec![];
+    }
+    let place = |kind: SceneryKind, meters: f32, offset: f32, yaw: f32| {
+        let (i, t) = track_config.segment_at_meter(meters);
+        let (_, quat) = track_config.get_transform_by_meter(meters);
+        let rotation = Quat::from_rotation_y(track_config.heading_at(meters) + yaw);
+        let translation = track_config.position_at(meters)
+            + quat.mul_vec3(Vec3::X) * offset
+            + track_config.normal_at(i, t) * kind.lift();
+        SceneryPlacement {
+            kind,
+            transform: Transform::from_translation(translation).with_rotation(rotation),
+        }
+    };
+    let mut placements: Vec<SceneryPlacement> = track_asset
+        .scenery
+        .iter()
+        .map(|item| place(item.kind, item.meters, item.offset, item.yaw.to_radians()))
+        .collect();
+
+    let (runoffs_left, runoffs_right) = (track_asset.runoffs_left(), track_asset.runoffs_right());
+    let length = track_config.track_length;
+    for rule in track_asset.scatter.iter() {
+        let mut rng = StdRng::seed_from_u64(rule.seed);
+        let count = (rule.density * length / 100.) as usize;
+        for side in [1., -1.] {
+            for _ in 0..count {
+                let meters = rng.gen_range(0. ..length);
+                let (i, t) = track_config.segment_at_meter(meters);
+                let j = (i + 1).min(track_config.left.len() - 1);
+                let half_width = track_config.left[i].distance(track_config.right[i]) / 2.;
+                let runoffs = if side > 0. {
+                    &runoffs_left
+                } else {
+                    &runoffs_right
+                };
+                let runoff = runoffs[i] + (runoffs[j] - runoffs[i]) * t;
+                let distance = rng.gen_range(0. ..=rule.depth);
+                let yaw = rng.gen_range(0. ..std::f32::consts::TAU);
+                let offset = side * (half_width + runoff + rule.from_wall + distance);
+                let placement = place(rule.kind, meters, offset, yaw);
+                // another part of the track may pass close by
+                if !track_config.is_within_edges(placement.transform.translation, runoff, None) {
+                    placements.push(placement);
+                }
+            }
+        }
+    }
+    placements
+}
+
+pub fn spawn_scenery(
+    cmd: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<StandardMaterial>,
+    placements: &[SceneryPlacement],
+) {
+    let mut handles: HashMap<SceneryKind, (Handle<Mesh>, Handle<StandardMaterial>)> =
+        HashMap::new();
+    for placement in placements {
+        let kind = placement.kind;
+        let (mesh, material) = handles.entry(kind).or_insert_with(|| {
+            (
+                meshes.add(kind.mesh()),
+                materials.add(StandardMaterial {
+                    base_color: kind.color(),
+                    perceptual_roughness: 0.9,
+                    ..default()
+                }),
+            )
+        });
+        let mut entity = cmd.spawn((
+            Mesh3d(mesh.clone()),
+            MeshMaterial3d(material.clone()),
+            placement.transform,
+            kind,
+            CullCell::default(),
+            TrackEntity,
+        ));
+        let Some(collider) = kind.collider() else {
+            continue;
+        };
+        match kind {
+            // cones get knocked over
+            SceneryKind::Cone => entity.insert((
+                collider,
+                RigidBody::Dynamic,
+                ColliderMassProperties::Mass(2.),
+            )),
+            _ => entity.insert((
+                collider,
+                RigidBody::Fixed,
+                CollisionGroups::new(STATIC_GROUP, Group::ALL),
+            )),
+        };
+    }
+}
@@ -0,0 +1,463 @@
+use crate::{
+    lap_timer_system, rolling_start_system, track_limits_system, CarTrack, GridLayout,
+    LapCompleted, SpawnCarOnTrackEvent, SpawnPosition, StageFinished, StartKind, TrackAsset,
+    TrackConfig, TrackHandle, TrackLimitsViolation, TrackLoadedEvent,
+};
+use bevy::prelude::*;
+use bevy_garage_car::{CarWheels, Player};
+use serde::{Deserialize, Serialize};
+
+/// Cars moving further than this in meters before lights out jumped the start.
+const JUMP_START_DISTANCE: f32 = 1.;
+
+/// Part of a race weekend, in the order they run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum SessionState {
+    #[default]
+    Practice,
+    Qualifying,
+    Grid,
+    /// Start lights coming on one by one.
+    Countdown,
+    Racing,
+    Finished,
+}
+
+/// Race weekend of a track.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SessionRules {
+    /// Practice length in seconds, zero skips it, open until the session is advanced when not set.
+    pub practice: Option<f32>,
+    /// Qualifying length in seconds, skipped when not set.
+    pub qualifying: Option<f32>,
+    /// Race distance of a closed track in laps.
+    pub laps: Option<u32>,
+    /// Race length in seconds, the leader's lap running at the time is the last.
+    pub time_limit: Option<f32>,
+    /// Seconds on the grid before the lights come on.
+    pub grid_time: f32,
+    pub lights: u32,
+    /// Seconds between the lights, and from the last one to lights out.
+    pub light_interval: f32,
+    /// Seconds added to the race time of a car moving before lights out.
+    pub jump_start_penalty: f32,
+    /// Seconds the field has to finish after the winner.
+    pub finish_timeout: f32,
+    pub grid: GridLayout,
+}
+
+impl Default for SessionRules {
+    fn default() -> Self {
+        Self {
+            practice: None,
+            qualifying: None,
+            laps: Some(3),
+            time_limit: None,
+            grid_time: 5.,
+            lights: 5,
+            light_interval: 1.,
+            jump_start_penalty: 5.,
+            finish_timeout: 120.,
+            grid: GridLayout::default(),
+        }
+    }
+}
+
+/// Car through the sessions, kept by car index as cars are respawned on the grid.
+#[derive(Debug, Clone, Default)]
+pub struct SessionCar {
+    pub index: usize,
+    pub laps: u32,
+    /// Best valid lap of the running session.
+    pub best_lap: Option<f32>,
+    /// Race time including penalties.
+    pub finish: Option<f32>,
+    pub jump_start: bool,
+    /// Track limits penalties of the race in seconds.
+    pub track_limits: f32,
+    grid_position: Option<Vec3>,
+    track_position: f32,
+}
+
+impl SessionCar {
+    /// Seconds added to the race time.
+    pub fn penalty(&self, jump_start_penalty: f32) -> f32 {
+        let jump_start = match self.jump_start {
+            true => jump_start_penalty,
+            false => 0.,
+        };
+        jump_start + self.track_limits
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SessionResult {
+    /// Place from 1.
+    pub place: usize,
+    pub index: usize,
+    pub laps: u32,
+    /// Race time including penalties, none for cars that didn't finish or timed sessions.
+    pub time: Option<f32>,
+    pub best_lap: Option<f32>,
+    /// Jump start and track limits penalties in seconds, included in the time.
+    pub penalty: f32,
+}
+
+#[derive(Debug, Clone, Message)]
+pub enum SessionEvent {
+    State(SessionState),
+    JumpStart {
+        index: usize,
+    },
+    CarFinished {
+        index: usize,
+        place: usize,
+    },
+    /// Classification at the end of practice, qualifying or the race.
+    Results {
+        state: SessionState,
+        results: Vec<SessionResult>,
+    },
+}
+
+/// Ends the running session early and moves on to the next one.
+#[derive(Debug, Message)]
+pub struct AdvanceSession;
+
+#[derive(Resource, Debug, Default)]
+pub struct Session {
+    pub rules: SessionRules,
+    pub state: SessionState,
+    /// Seconds since the state began, race time while racing.
+    pub time: f32,
+    /// Start lights on during the countdown.
+    pub lights: u32,
+    pub cars: Vec<SessionCar>,
+    /// Car indexes in starting order, set by qualifying.
+    pub grid: Vec<usize>,
+    /// Race time the winner finished at, every car finishes on its next lap from then on.
+    pub chequered: Option<f32>,
+}
+
+impl Session {
+    fn next_state(&self) -> SessionState {
+        match self.state {
+            SessionState::Practice if self.rules.qualifying.is_some() => SessionState::Qualifying,
+            SessionState::Practice | SessionState::Qualifying => SessionState::Grid,
+            SessionState::Grid => SessionState::Countdown,
+            SessionState::Countdown => SessionState::Racing,
+            SessionState::Racing => SessionState::Finished,
+            SessionState::Finished => SessionState::Practice,
+        }
+    }
+    fn car_mut(&mut self, index: usize) -> &mut SessionCar {
+        match self.cars.iter().position(|car| car.index == index) {
+            Some(i) => &mut self.cars[i],
+            None => {
+                self.cars.push(SessionCar { index, ..default() });
+                self.cars.last_mut().unwrap()
+            }
+        }
+    }
+    /// Race laps the leader has to complete, none for a time limited race.
+    pub fn laps(&self) -> Option<u32> {
+        self.rules.laps.filter(|_| self.rules.time_limit.is_none())
+    }
+    /// Classification of the running session, by best lap unless it is the race.
+    pub fn results(&self) -> Vec<SessionResult> {
+        let mut cars: Vec<&SessionCar> = self.cars.iter().collect();
+        match self.state {
+            SessionState::Practice | SessionState::Qualifying => cars.sort_by(|a, b| {
+                let time = |car: &SessionCar| car.best_lap.unwrap_or(f32::MAX);
+                time(a).total_cmp(&time(b))
+            }),
+            _ => cars.sort_by(|a, b| match (a.finish, b.finish) {
+                (Some(a), Some(b)) => a.total_cmp(&b),
+                (Some(_), None) => std::cmp::Ordering::Less,
+                (None, Some(_)) => std::cmp::Ordering::Greater,
+                (None, None) => (b.laps, b.track_position)
+                    .partial_cmp(&(a.laps, a.track_position))
+                    .unwrap_or(std::cmp::Ordering::Equal),
+            }),
+        }
+        let timed = matches!(
+            self.state,
+            SessionState::Practice | SessionState::Qualifying
+        );
+        cars.iter()
+            .enumerate()
+            .map(|(i, car)| SessionResult {
+                place: i + 1,
+                index: car.index,
+                laps: car.laps,
+                time: car.finish.filter(|_| !timed),
+                best_lap: car.best_lap,
+                penalty: match timed {
+                    true => 0.,
+                    false => car.penalty(self.rules.jump_start_penalty),
+                },
+            })
+            .collect()
+    }
+    fn enter(&mut self, state: SessionState, events: &mut MessageWriter<SessionEvent>) {
+        if matches!(
+            self.state,
+            SessionState::Practice | SessionState::Qualifying
+        ) && self.state != state
+        {
+            let results = self.results();
+            if self.state == SessionState::Qualifying {
+                self.grid = results.iter().map(|result| result.index).collect();
+            }
+            events.write(SessionEvent::Results {
+                state: self.state,
+                results,
+            });
+        }
+        self.state = state;
+        self.time = 0.;
+        self.lights = 0;
+        match state {
+            SessionState::Practice | SessionState::Qualifying | SessionState::Grid => {
+                self.chequered = None;
+                for car in self.cars.iter_mut() {
+                    *car = SessionCar {
+                        index: car.index,
+                        ..default()
+                    };
+                }
+            }
+            SessionState::Finished => {
+                let results = self.results();
+                events.write(SessionEvent::Results {
+                    state: SessionState::Racing,
+                    results,
+                });
+            }
+            _ => {}
+        }
+        events.write(SessionEvent::State(state));
+    }
+    fn finish(&mut self, index: usize, events: &mut MessageWriter<SessionEvent>) {
+        let (time, jump_start_penalty) = (self.time, self.rules.jump_start_penalty);
+        let car = self.car_mut(index);
+        if car.finish.is_some() {
+            return;
+        }
+        car.finish = Some(time + car.penalty(jump_start_penalty));
+        self.chequered.get_or_insert(time);
+        let place = self.cars.iter().filter(|car| car.finish.is_some()).count();
+        events.write(SessionEvent::CarFinished { index, place });
+    }
+}
+
+pub struct SessionPlugin;
+
+impl Plugin for SessionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Session>()
+            .add_message::<SessionEvent>()
+            .add_message::<AdvanceSession>()
+            .add_systems(
+                Update,
+                (
+                    session_start_system.run_if(on_message::<TrackLoadedEvent>),
+                    session_lap_system
+                        .after(lap_timer_system)
+                        .after(track_limits_system),
+                    session_state_system.after(session_lap_system),
+                    session_grid_system.after(session_state_system),
+                    rolling_start_system,
+                ),
+            );
+    }
+}
+
+/// Starts the weekend over with the rules of the loaded track.
+pub fn session_start_system(
+    mut session: ResMut<Session>,
+    track_handle: Res<TrackHandle>,
+    tracks: Res<Assets<TrackAsset>>,
+    mut events: MessageWriter<SessionEvent>,
+) {
+    let Some(track_asset) = tracks.get(&track_handle.0) else {
+        return;
+    };
+    *session = Session {
+        rules: track_asset.session.clone().unwrap_or_default(),
+        ..default()
+    };
+    session.enter(SessionState::Practice, &mut events);
+}
+
+/// Counts laps and track limits penalties, keeps the best laps and finishes cars once the
+/// chequered flag is out.
+pub fn session_lap_system(
+    mut session: ResMut<Session>,
+    mut lap_events: MessageReader<LapCompleted>,
+    mut stage_events: MessageReader<StageFinished>,
+    mut violations: MessageReader<TrackLimitsViolation>,
+    mut events: MessageWriter<SessionEvent>,
+) {
+    let state = session.state;
+    for violation in violations.read() {
+        if let (SessionState::Racing, Some(penalty)) = (state, violation.penalty) {
+            let car = session.car_mut(violation.index);
+            if car.finish.is_none() {
+                car.track_limits += penalty;
+            }
+        }
+    }
+    for lap_event in lap_events.read() {
+        if matches!(
+            state,
+            SessionState::Grid | SessionState::Countdown | SessionState::Finished
+        ) {
+            continue;
+        }
+        let car = session.car_mut(lap_event.index);
+        car.laps += 1;
+        if lap_event.lap.valid && car.best_lap.is_none_or(|best| lap_event.lap.time < best) {
+            car.best_lap = Some(lap_event.lap.time);
+        }
+        let laps = car.laps;
+        if state != SessionState::Racing {
+            continue;
+        }
+        let last_lap = session.laps().is_some_and(|race_laps| laps >= race_laps)
+            || session
+                .rules
+                .time_limit
+                .is_some_and(|limit| session.time >= limit);
+        if session.chequered.is_some() || last_lap {
+            session.finish(lap_event.index, &mut events);
+        }
+    }
+    for stage_event in stage_events.read() {
+        if state == SessionState::Racing {
+            session.finish(stage_event.index, &mut events);
+        }
+    }
+}
+
+/// Runs the session clock, the start lights and jump start checks, and moves between states.
+pub fn session_state_system(
+    time: Res<Time>,
+    mut session: ResMut<Session>,
+    mut advance_events: MessageReader<AdvanceSession>,
+    track_config: Res<TrackConfig>,
+    cars: Query<(&Transform, &CarTrack)>,
+    mut events: MessageWriter<SessionEvent>,
+) {
+    session.time += time.delta_secs();
+    let mut crossed = false;
+    for (_, car_track) in cars.iter() {
+        let car = session.car_mut(car_track.index);
+        crossed |= match track_config.closed {
+            true => car.track_position - car_track.track_position > track_config.track_length / 2.,
+            false => car.track_position < 0. && car_track.track_position >= 0.,
+        };
+        car.track_position = car_track.track_position;
+    }
+    if advance_events.read().count() > 0 {
+        let next = session.next_state();
+        session.enter(next, &mut events);
+    }
+    let rules = session.rules.clone();
+    match session.state {
+        SessionState::Practice if rules.practice.is_some_and(|t| session.time >= t) => {
+            let next = session.next_state();
+            session.enter(next, &mut events);
+        }
+        SessionState::Qualifying if rules.qualifying.is_some_and(|t| session.time >= t) => {
+            session.enter(SessionState::Grid, &mut events);
+        }
+        SessionState::Grid if rules.grid.start == StartKind::Rolling => {
+            // the formation gets the green flag as the first car reaches the line
+            if crossed {
+                session.enter(SessionState::Racing, &mut events);
+            }
+        }
+        SessionState::Grid if session.time >= rules.grid_time => {
+            session.enter(SessionState::Countdown, &mut events);
+            // cars have settled on the grid, moving from here on is a jump start
+            for (transform, car_track) in cars.iter() {
+                session.car_mut(car_track.index).grid_position = Some(transform.translation);
+            }
+        }
+        SessionState::Countdown => {
+            if session.time >= rules.light_interval * rules.lights as f32 {
+                session.enter(SessionState::Racing, &mut events);
+                return;
+            }
+            session.lights = ((session.time / rules.light_interval) as u32 + 1).min(rules.lights);
+            for (transform, car_track) in cars.iter() {
+                let car = session.car_mut(car_track.index);
+                let moved = car.grid_position.is_some_and(|grid_position| {
+                    grid_position.xz().distance(transform.translation.xz()) > JUMP_START_DISTANCE
+                });
+                if moved && !car.jump_start {
+                    car.jump_start = true;
+                    events.write(SessionEvent::JumpStart {
+                        index: car_track.index,
+                    });
+                }
+            }
+        }
+        SessionState::Racing => {
+            let racing = cars.iter().len();
+            let finished = session
+                .cars
+                .iter()
+                .filter(|car| car.finish.is_some())
+                .count();
+            let timeout = session
+                .chequered
+                .is_some_and(|chequered| session.time - chequered >= rules.finish_timeout);
+            if racing > 0 && finished >= racing || timeout {
+                session.enter(SessionState::Finished, &mut events);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Brings the cars back to the start line in grid order when the grid forms.
+pub fn session_grid_system(
+    mut cmd: Commands,
+    session: Res<Session>,
+    mut session_events: MessageReader<SessionEvent>,
+    mut cars: Query<(Entity, &CarTrack, &mut CarWheels, Has<Player>)>,
+    mut spawn_events: MessageWriter<SpawnCarOnTrackEvent>,
+) {
+    if !session_events
+        .read()
+        .any(|event| matches!(event, SessionEvent::State(SessionState::Grid)))
+    {
+        return;
+    }
+    let mut grid: Vec<(usize, bool)> = vec![];
+    for (e, car_track, mut wheels, player) in cars.iter_mut() {
+        cmd.entity(e).despawn();
+        wheels.despawn(&mut cmd);
+        grid.push((car_track.index, player));
+    }
+    // qualified cars first, the rest in car order
+    let slot = |index: usize| {
+        session
+            .grid
+            .iter()
+            .position(|i| *i == index)
+            .unwrap_or(session.grid.len() + index)
+    };
+    grid.sort_by_key(|(index, _)| slot(*index));
+    for (slot, (index, player)) in grid.into_iter().enumerate() {
+        spawn_events.write(SpawnCarOnTrackEvent {
+            player,
+            index,
+            count: 1,
+            position: SpawnPosition::Grid(slot),
+        });
+    }
+}
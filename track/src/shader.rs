@@ -39,6 +39,13 @@ impl MaterialExtension for GroundExtension {
 pub struct AsphaltExtension {
     #[uniform(100)]
     pub quality: i32,
+    /// Water on the road from 0 dry to 1 soaked.
+    #[uniform(100)]
+    pub wetness: f32,
+    /// Rubber laid on the road, sampled through the second uv set of the main track.
+    #[texture(101)]
+    #[sampler(102)]
+    pub rubber: Option<Handle<Image>>,
 }
 impl MaterialExtension for AsphaltExtension {
     fn fragment_shader() -> ShaderRef {
@@ -0,0 +1,345 @@
+use crate::{contact_slip, Surface, TrackEntity, WheelSurface};
+use bevy::asset::RenderAssetUsages;
+use bevy::camera::visibility::NoFrustumCulling;
+use bevy::light::NotShadowCaster;
+use bevy::mesh::{Indices, PrimitiveTopology};
+use bevy::prelude::*;
+use bevy_garage_car::Wheel;
+use bevy_rapier3d::prelude::*;
+use std::collections::{HashMap, VecDeque};
+
+/// Points kept per surface, the oldest marks go first.
+const SKID_MAX_POINTS: usize = 4096;
+/// Seconds for a mark to fade away.
+const SKID_FADE: f32 = 60.;
+/// Seconds between mesh rebuilds while marks only fade.
+const SKID_FADE_INTERVAL: f32 = 0.5;
+/// Distance between recorded points of a mark, in meters.
+const SKID_STEP: f32 = 0.3;
+/// Points further apart than this start a new mark.
+const SKID_MAX_GAP: f32 = 2.;
+/// Height of the marks above the contact point, keeps them off the road surface.
+const SKID_LIFT: f32 = 0.02;
+
+/// Slip speed in m/s a tyre leaves a mark above, soft ground marks at a lower slip.
+fn skid_threshold(surface: Surface) -> f32 {
+    match surface {
+        Surface::Grass | Surface::Gravel | Surface::Sand => 1.,
+        _ => 3.,
+    }
+}
+
+/// Black rubber on hard surfaces, ruts on soft ground.
+fn skid_color(surface: Surface) -> Color {
+    match surface {
+        Surface::Grass => Color::srgba(0.2, 0.14, 0.07, 0.9),
+        Surface::Gravel | Surface::Sand => Color::srgba(0.3, 0.25, 0.18, 0.8),
+        Surface::WetAsphalt => Color::srgba(0.02, 0.02, 0.02, 0.5),
+        _ => Color::srgba(0.02, 0.02, 0.02, 0.8),
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SkidPoint {
+    /// Wheel that laid the point, a mark joins only points of the same wheel.
+    pub wheel: Entity,
+    pub position: Vec3,
+    /// Half the mark width across the tyre.
+    pub across: Vec3,
+    pub normal: Vec3,
+    /// Darkness from 0 to 1 when the point was laid.
+    pub strength: f32,
+    pub time: f32,
+    /// Starts a new mark instead of joining the wheel's point before it.
+    pub first: bool,
+}
+
+/// Ribbon vertices of skid marks, two per point, alpha faded with age.
+#[derive(Debug, Default)]
+pub struct SkidRibbon {
+    pub positions: Vec<[f32; 3]>,
+    pub normals: Vec<[f32; 3]>,
+    pub colors: Vec<[f32; 4]>,
+    pub indices: Vec<u32>,
+}
+
+impl SkidRibbon {
+    pub fn mesh(self) -> Mesh {
+        let mut mesh = Mesh::new(
+            PrimitiveTopology::TriangleList,
+            RenderAssetUsages::RENDER_WORLD,
+        );
+        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, self.positions);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, self.normals);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, self.colors);
+        mesh.insert_indices(Indices::U32(self.indices));
+        mesh
+    }
+}
+
+/// Quads between neighbouring points of every mark, fading out over `fade` seconds to `now`.
+pub fn skid_ribbon<'a>(
+    points: impl IntoIterator<Item = &'a SkidPoint>,
+    now: f32,
+    fade: f32,
+) -> SkidRibbon {
+    let mut ribbon = SkidRibbon::default();
+    // first vertex of the last point of every wheel, the wheels' points are interleaved
+    let mut last: HashMap<Entity, u32> = HashMap::new();
+    for point in points {
+        let i = ribbon.positions.len() as u32;
+        if let Some(j) = last.insert(point.wheel, i).filter(|_| !point.first) {
+            // i---i+1
+            // | \ |
+            // j---j+1
+            ribbon.indices.extend([j, j + 1, i, i, j + 1, i + 1]);
+        }
+        let alpha = point.strength * (1. - (now - point.time) / fade).clamp(0., 1.);
+        for side in [-1., 1.] {
+            ribbon
+                .positions
+                .push((point.position + point.across * side).to_array());
+            ribbon.normals.push(point.normal.to_array());
+            ribbon.colors.push([1., 1., 1., alpha]);
+        }
+    }
+    ribbon
+}
+
+/// Adds a point to a trail, dropping the oldest one over `SKID_MAX_POINTS`.
+fn push_point(points: &mut VecDeque<SkidPoint>, point: SkidPoint) {
+    points.push_back(point);
+    if points.len() > SKID_MAX_POINTS {
+        points.pop_front();
+    }
+}
+
+/// Marks on one surface, drawn as a single mesh.
+#[derive(Debug)]
+pub struct SkidTrail {
+    pub surface: Surface,
+    pub points: VecDeque<SkidPoint>,
+    pub mesh: Handle<Mesh>,
+    entity: Entity,
+    changed: bool,
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct SkidMarks {
+    pub trails: Vec<SkidTrail>,
+    /// Surface and position of the last point laid by every marking wheel.
+    wheels: HashMap<Entity, (Surface, Vec3)>,
+    /// Seconds since the meshes were last rebuilt for fading.
+    faded: f32,
+}
+
+pub fn skid_start_system(mut skid_marks: ResMut<SkidMarks>) {
+    // the mark entities go with the other track entities
+    *skid_marks = SkidMarks::default();
+}
+
+/// Lays points under wheels slipping over their surface threshold and rebuilds the mark meshes.
+pub fn skid_system(
+    mut cmd: Commands,
+    time: Res<Time>,
+    mut skid_marks: ResMut<SkidMarks>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    wheels: Query<(Entity, &Transform, &Velocity, &Wheel, &WheelSurface)>,
+    mut visibilities: Query<&mut Visibility>,
+) {
+    let now = time.elapsed_secs();
+    let skid_marks = &mut *skid_marks;
+    for (e, transform, velocity, wheel, wheel_surface) in wheels.iter() {
+        let slip = contact_slip(velocity, wheel.radius);
+        let surface = match wheel_surface.surface {
+            Some(surface) if slip > skid_threshold(surface) => surface,
+            _ => {
+                skid_marks.wheels.remove(&e);
+                continue;
+            }
+        };
+        let normal = wheel_surface.normal;
+        let position = transform.translation - normal * (wheel.radius - SKID_LIFT);
+        // distance from the last point of the wheel on the same surface
+        let from_last = skid_marks
+            .wheels
+            .get(&e)
+            .filter(|(s, _)| *s == surface)
+            .map(|(_, p)| p.distance(position));
+        if from_last.is_some_and(|d| d < SKID_STEP) {
+            continue;
+        }
+        let axle = (transform.rotation * Vec3::X).reject_from(normal);
+        let point = SkidPoint {
+            wheel: e,
+            position,
+            across: axle.normalize_or_zero() * wheel.width / 2.,
+            normal,
+            strength: (slip / skid_threshold(surface) - 1.).clamp(0.3, 1.),
+            time: now,
+            first: !from_last.is_some_and(|d| d < SKID_MAX_GAP),
+        };
+        let trail_i = match skid_marks.trails.iter().position(|t| t.surface == surface) {
+            Some(trail_i) => trail_i,
+            None => {
+                let mesh = meshes.add(SkidRibbon::default().mesh());
+                let entity = cmd
+                    .spawn((
+                        Mesh3d(mesh.clone()),
+                        MeshMaterial3d(materials.add(StandardMaterial {
+                            base_color: skid_color(surface),
+                            alpha_mode: AlphaMode::Blend,
+                            perceptual_roughness: 1.,
+                            // marks run either way along the wheel axle
+                            double_sided: true,
+                            cull_mode: None,
+                            ..default()
+                        })),
+                        Transform::IDENTITY,
+                        Visibility::Hidden,
+                        NotShadowCaster,
+                        NoFrustumCulling,
+                        TrackEntity,
+                    ))
+                    .id();
+                skid_marks.trails.push(SkidTrail {
+                    surface,
+                    points: VecDeque::new(),
+                    mesh,
+                    entity,
+                    changed: false,
+                });
+                skid_marks.trails.len() - 1
+            }
+        };
+        let trail = &mut skid_marks.trails[trail_i];
+        push_point(&mut trail.points, point);
+        trail.changed = true;
+        skid_marks.wheels.insert(e, (surface, position));
+    }
+
+    skid_marks.faded += time.delta_secs();
+    let fading = skid_marks.faded >= SKID_FADE_INTERVAL;
+    if fading {
+        skid_marks.faded = 0.;
+    }
+    for trail in skid_marks.trails.iter_mut() {
+        while trail
+            .points
+            .front()
+            .is_some_and(|p| now - p.time > SKID_FADE)
+        {
+            trail.points.pop_front();
+        }
+        if !trail.changed && !(fading && !trail.points.is_empty()) {
+            continue;
+        }
+        trail.changed = false;
+        let ribbon = skid_ribbon(&trail.points, now, SKID_FADE);
+        if let Ok(mut visibility) = visibilities.get_mut(trail.entity) {
+            *visibility = match ribbon.indices.is_empty() {
+                true => Visibility::Hidden,
+                false => Visibility::Inherited,
+            };
+        }
+        if let Some(mesh) = meshes.get_mut(&trail.mesh) {
+            *mesh = ribbon.mesh();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wheels(count: usize) -> Vec<Entity> {
+        let mut world = World::new();
+        (0..count).map(|_| world.spawn_empty().id()).collect()
+    }
+
+    fn point(wheel: Entity, x: f32, z: f32, time: f32, first: bool) -> SkidPoint {
+        SkidPoint {
+            wheel,
+            position: Vec3::new(x, 0., z),
+            across: Vec3::X * 0.1,
+            normal: Vec3::Y,
+            strength: 1.,
+            time,
+            first,
+        }
+    }
+
+    #[test]
+    fn vertex_and_index_counts() {
+        let wheel = wheels(1)[0];
+        let points: Vec<SkidPoint> = (0..5)
+            .map(|i| point(wheel, 0., i as f32 * SKID_STEP, 0., i == 0))
+            .collect();
+        let ribbon = skid_ribbon(&points, 0., SKID_FADE);
+        assert_eq!(ribbon.positions.len(), 10);
+        assert_eq!(ribbon.normals.len(), 10);
+        assert_eq!(ribbon.colors.len(), 10);
+        assert_eq!(ribbon.indices.len(), 4 * 6);
+        assert!(ribbon.indices.iter().all(|i| (*i as usize) < 10));
+    }
+
+    #[test]
+    fn first_point_starts_a_new_mark() {
+        let wheel = wheels(1)[0];
+        let points: Vec<SkidPoint> = (0..4)
+            .map(|i| point(wheel, 0., i as f32 * 3., 0., i == 0 || i == 2))
+            .collect();
+        let ribbon = skid_ribbon(&points, 0., SKID_FADE);
+        assert_eq!(ribbon.positions.len(), 8);
+        // 0-1 and 2-3 are joined, nothing joins 1 to 2
+        assert_eq!(ribbon.indices, vec![0, 1, 2, 2, 1, 3, 4, 5, 6, 6, 5, 7]);
+    }
+
+    #[test]
+    fn wheels_make_separate_marks() {
+        let wheels = wheels(2);
+        // two wheels laying points in turn, as the system does every frame
+        let points: Vec<SkidPoint> = (0..6)
+            .map(|i| {
+                let wheel = i % 2;
+                point(wheels[wheel], wheel as f32 * 1.6, (i / 2) as f32, 0., i < 2)
+            })
+            .collect();
+        let ribbon = skid_ribbon(&points, 0., SKID_FADE);
+        assert_eq!(ribbon.indices.len(), 4 * 6);
+        for triangle in ribbon.indices.chunks(3) {
+            let wheel = |i: u32| points[i as usize / 2].wheel;
+            assert_eq!(wheel(triangle[0]), wheel(triangle[1]));
+            assert_eq!(wheel(triangle[0]), wheel(triangle[2]));
+        }
+    }
+
+    #[test]
+    fn marks_fade_with_age() {
+        let wheel = wheels(1)[0];
+        let mut faint = point(wheel, 0., 1., 10., false);
+        faint.strength = 0.5;
+        let points = [point(wheel, 0., 0., 0., true), faint];
+        let alpha = |now: f32| {
+            let ribbon = skid_ribbon(&points, now, 20.);
+            [ribbon.colors[0][3], ribbon.colors[2][3]]
+        };
+        assert_eq!(alpha(0.), [1., 0.5]);
+        assert_eq!(alpha(10.), [0.5, 0.5]);
+        assert_eq!(alpha(20.), [0., 0.25]);
+        assert_eq!(alpha(40.), [0., 0.]);
+    }
+
+    #[test]
+    fn trails_keep_the_newest_points() {
+        let wheel = wheels(1)[0];
+        let mut points = VecDeque::new();
+        for i in 0..SKID_MAX_POINTS + 10 {
+            push_point(&mut points, point(wheel, 0., 0., i as f32, false));
+        }
+        assert_eq!(points.len(), SKID_MAX_POINTS);
+        assert_eq!(points.front().unwrap().time, 10.);
+        assert_eq!(points.back().unwrap().time, (SKID_MAX_POINTS + 9) as f32);
+    }
+}
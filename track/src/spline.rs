@@ -0,0 +1,144 @@
+use bevy::prelude::*;
+
+/// Max heading change in radians between resampled points.
+const SPLINE_MAX_TURN: f32 = 0.04;
+/// Max distance in meters between resampled points.
+const SPLINE_MAX_STEP: f32 = 10.;
+
+#[derive(Debug, Clone, Copy)]
+pub struct SplineSample {
+    pub position: Vec3,
+    pub tangent: Vec3,
+    /// Signed horizontal curvature in 1/m, positive when turning left.
+    pub curvature: f32,
+    /// Control point span the sample lies on and the parameter within it.
+    pub span: usize,
+    pub t: f32,
+}
+
+/// Centripetal Catmull-Rom spline through the track control points.
+#[derive(Debug, Clone)]
+pub struct TrackSpline {
+    points: Vec<Vec3>,
+    closed: bool,
+}
+
+impl TrackSpline {
+    /// Control points without the closing duplicate.
+    pub fn new(points: Vec<Vec3>, closed: bool) -> Self {
+        Self { points, closed }
+    }
+
+    pub fn spans(&self) -> usize {
+        match (self.points.len(), self.closed) {
+            (0 | 1, _) => 0,
+            (n, true) => n,
+            (n, false) => n - 1,
+        }
+    }
+
+    fn point(&self, i: isize) -> Vec3 {
+        let n = self.points.len() as isize;
+        if self.closed {
+            return self.points[i.rem_euclid(n) as usize];
+        }
+        // open ends are extended by mirroring the neighbouring point
+        match i {
+            i if i < 0 => 2. * self.points[0] - self.points[1],
+            i if i >= n => 2. * self.points[n as usize - 1] - self.points[n as usize - 2],
+            i => self.points[i as usize],
+        }
+    }
+
+    /// Span as a cubic Hermite segment: start, start tangent, end, end tangent.
+    fn hermite(&self, i: usize) -> [Vec3; 4] {
+        let i = i as isize;
+        let (p0, p1, p2, p3) = (
+            self.point(i - 1),
+            self.point(i),
+            self.point(i + 1),
+            self.point(i + 2),
+        );
+        let knot = |a: Vec3, b: Vec3| a.distance(b).sqrt().max(0.0001);
+        let (d0, d1, d2) = (knot(p0, p1), knot(p1, p2), knot(p2, p3));
+        let m1 = (p1 - p0) / d0 - (p2 - p0) / (d0 + d1) + (p2 - p1) / d1;
+        let m2 = (p2 - p1) / d1 - (p3 - p1) / (d1 + d2) + (p3 - p2) / d2;
+        [p1, m1 * d1, p2, m2 * d1]
+    }
+
+    pub fn position(&self, i: usize, t: f32) -> Vec3 {
+        let [p1, m1, p2, m2] = self.hermite(i);
+        let (t2, t3) = (t * t, t * t * t);
+        p1 * (2. * t3 - 3. * t2 + 1.)
+            + m1 * (t3 - 2. * t2 + t)
+            + p2 * (-2. * t3 + 3. * t2)
+            + m2 * (t3 - t2)
+    }
+
+    fn velocity(&self, i: usize, t: f32) -> Vec3 {
+        let [p1, m1, p2, m2] = self.hermite(i);
+        let t2 = t * t;
+        p1 * (6. * t2 - 6. * t)
+            + m1 * (3. * t2 - 4. * t + 1.)
+            + p2 * (-6. * t2 + 6. * t)
+            + m2 * (3. * t2 - 2. * t)
+    }
+
+    fn acceleration(&self, i: usize, t: f32) -> Vec3 {
+        let [p1, m1, p2, m2] = self.hermite(i);
+        p1 * (12. * t - 6.) + m1 * (6. * t - 4.) + p2 * (-12. * t + 6.) + m2 * (6. * t - 2.)
+    }
+
+    pub fn sample(&self, i: usize, t: f32) -> SplineSample {
+        let v = self.velocity(i, t);
+        let a = self.acceleration(i, t);
+        let speed_flat = v.xz().length();
+        let curvature = if speed_flat > 0. {
+            (v.z * a.x - v.x * a.z) / speed_flat.powi(3)
+        } else {
+            0.
+        };
+        let chord = (self.point(i as isize + 1) - self.point(i as isize)).normalize_or_zero();
+        SplineSample {
+            position: self.position(i, t),
+            tangent: v.normalize_or(chord),
+            curvature,
+            span: i,
+            t,
+        }
+    }
+
+    /// Samples along the spline, denser where it turns. Closed splines repeat the first sample.
+    pub fn resample(&self) -> Vec<SplineSample> {
+        let mut samples: Vec<SplineSample> = Vec::new();
+        for i in 0..self.spans() {
+            let (v0, v1, v2) = (
+                self.velocity(i, 0.),
+                self.velocity(i, 0.5),
+                self.velocity(i, 1.),
+            );
+            let turn = angle(v0, v1) + angle(v1, v2);
+            let length = self.point(i as isize).distance(self.point(i as isize + 1));
+            let steps = (length / SPLINE_MAX_STEP)
+                .ceil()
+                .max((turn / SPLINE_MAX_TURN).ceil())
+                .max(1.) as usize;
+            for step in 0..steps {
+                samples.push(self.sample(i, step as f32 / steps as f32));
+            }
+        }
+        match (self.closed, self.spans()) {
+            (_, 0) => {}
+            (true, _) => samples.push(samples[0]),
+            (false, spans) => samples.push(self.sample(spans - 1, 1.)),
+        }
+        samples
+    }
+}
+
+fn angle(a: Vec3, b: Vec3) -> f32 {
+    match (a.try_normalize(), b.try_normalize()) {
+        (Some(a), Some(b)) => a.dot(b).clamp(-1., 1.).acos(),
+        _ => 0.,
+    }
+}
@@ -0,0 +1,202 @@
+use crate::{track_coordinates, GripField, RacingLine, TrackConfig, Weather};
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy_garage_car::{CarWheels, Wheel};
+use bevy_rapier3d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Surface material of a track collider.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Surface {
+    Asphalt,
+    WetAsphalt,
+    Kerb,
+    Grass,
+    Gravel,
+    Sand,
+    Concrete,
+}
+
+impl Surface {
+    /// Friction coefficient of a tyre on this surface, from dry to soaked.
+    pub fn friction(self, wetness: f32) -> f32 {
+        let (dry, wet) = match self {
+            Surface::Asphalt => (5., 3.),
+            Surface::WetAsphalt => (3.5, 3.),
+            Surface::Kerb => (4., 2.),
+            Surface::Grass => (3., 2.),
+            Surface::Gravel => (2.5, 2.2),
+            Surface::Sand => (2., 2.),
+            Surface::Concrete => (0.1, 0.1),
+        };
+        dry + (wet - dry) * wetness
+    }
+    /// Rolling resistance coefficient, the share of the wheel load pulling against its motion.
+    pub fn rolling_resistance(self) -> f32 {
+        match self {
+            Surface::Asphalt | Surface::WetAsphalt | Surface::Concrete => 0.015,
+            Surface::Kerb => 0.02,
+            Surface::Grass => 0.08,
+            Surface::Gravel => 0.3,
+            Surface::Sand => 0.4,
+        }
+    }
+    /// Friction of a contact between two surfaces, tyres are the surface-less side.
+    pub fn pair_friction(a: Option<Surface>, b: Option<Surface>, wetness: f32) -> Option<f32> {
+        match (a, b) {
+            (Some(a), Some(b)) => Some(a.friction(wetness).min(b.friction(wetness))),
+            (Some(s), None) | (None, Some(s)) => Some(s.friction(wetness)),
+            (None, None) => None,
+        }
+    }
+    /// Surface of a contact, a run-off zone replaces the grass.
+    pub fn in_zone(self, zone: Option<Surface>) -> Surface {
+        match (self, zone) {
+            (Surface::Grass, Some(zone)) => zone,
+            _ => self,
+        }
+    }
+}
+
+/// Run-off surface between two distances from the start line, replacing the grass.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct SurfaceZone {
+    pub from: f32,
+    pub to: f32,
+    pub surface: Surface,
+}
+
+/// Surface under the wheel, none when it is in the air.
+#[derive(Component, Debug, Default)]
+pub struct WheelSurface {
+    pub surface: Option<Surface>,
+    /// Ground normal at the contact of that surface, pointing up at the wheel.
+    pub normal: Vec3,
+    pub wetness: f32,
+    /// Rubber laid on the asphalt under the wheel from 0 clean to 1 rubbered in.
+    pub rubber: f32,
+    /// Distance along the centerline and lateral position from 0 at the right edge to 1 at the left,
+    /// none off the road.
+    pub coordinates: Option<(f32, f32)>,
+    /// Run-off zone under the wheel, read by the physics hooks for its grass contacts.
+    pub zone: Option<Surface>,
+    /// Centerline segment the wheel was projected on last, where the next search starts.
+    pub segment: Option<usize>,
+}
+
+/// Speed the contact patch slides over the ground, zero when the tyre rolls without slipping.
+pub fn contact_slip(velocity: &Velocity, radius: f32) -> f32 {
+    let contact = velocity.linvel + velocity.angvel.cross(-Vec3::Y * radius);
+    contact.reject_from(Vec3::Y).length()
+}
+
+/// Weather, rubber and the racing line, what the road is like under a wheel.
+#[derive(SystemParam)]
+pub struct RoadConditions<'w> {
+    weather: Res<'w, Weather>,
+    grip_field: Res<'w, GripField>,
+    racing_line: Res<'w, RacingLine>,
+}
+
+pub fn wheel_surface_system(
+    mut cmd: Commands,
+    rapier_context: ReadRapierContext,
+    track_config: Res<TrackConfig>,
+    road: RoadConditions,
+    mut wheels: Query<(Entity, &Transform, Option<&mut WheelSurface>), With<Wheel>>,
+    surfaces: Query<&Surface>,
+) {
+    let Ok(ctx) = rapier_context.single() else {
+        return;
+    };
+    for (e, transform, wheel_surface) in wheels.iter_mut() {
+        let hint = wheel_surface.as_ref().and_then(|w| w.segment);
+        let projection = track_config.project_near(transform.translation, hint);
+        let zone = match track_config.surfaces.is_empty() {
+            true => None,
+            false => projection
+                .as_ref()
+                .and_then(|projection| track_config.zone_surface(projection.distance)),
+        };
+        let contact = ctx
+            .contact_pairs_with(e)
+            .filter(|pair| pair.has_any_active_contact())
+            .filter_map(|pair| {
+                // manifold normals point from the first collider to the second
+                let (other, side) = match pair.collider1() == Some(e) {
+                    true => (pair.collider2(), -1.),
+                    false => (pair.collider1(), 1.),
+                };
+                let normal = pair
+                    .manifolds()
+                    .find(|manifold| manifold.num_points() > 0)
+                    .map_or(Vec3::Y, |manifold| manifold.normal() * side);
+                Some((surfaces.get(other?).ok()?.in_zone(zone), normal))
+            })
+            // touching the road and the ground at once, the road carries the wheel
+            .max_by(|(a, _), (b, _)| a.friction(0.).total_cmp(&b.friction(0.)));
+        let surface = contact.map(|(surface, _)| surface);
+        let normal = contact.map_or(Vec3::Y, |(_, normal)| normal);
+        // cars dry the racing line, the centerline until the line is solved
+        let from_line = projection.as_ref().map(|projection| {
+            road.racing_line
+                .position_at(projection.segment, projection.t)
+                .map_or(projection.offset, |p| {
+                    p.xz().distance(transform.translation.xz())
+                })
+        });
+        let wetness = road.weather.wetness_at(from_line);
+        let coordinates = projection.as_ref().and_then(|projection| {
+            track_coordinates(&track_config, projection, transform.translation)
+        });
+        let rubber = match (surface, coordinates) {
+            (Some(Surface::Asphalt), Some((distance, lateral))) => {
+                road.grip_field.rubber_at(distance, lateral)
+            }
+            _ => 0.,
+        };
+        match wheel_surface {
+            Some(mut wheel_surface) => {
+                wheel_surface.surface = surface;
+                wheel_surface.normal = normal;
+                wheel_surface.wetness = wetness;
+                wheel_surface.rubber = rubber;
+                wheel_surface.coordinates = coordinates;
+                wheel_surface.zone = zone;
+                wheel_surface.segment = projection.map(|projection| projection.segment);
+            }
+            None => {
+                cmd.entity(e).insert(WheelSurface {
+                    surface,
+                    normal,
+                    wetness,
+                    rubber,
+                    coordinates,
+                    zone,
+                    segment: projection.map(|projection| projection.segment),
+                });
+            }
+        }
+    }
+}
+
+/// Pulls wheels against their motion with the rolling resistance of the surface they are on.
+pub fn surface_drag_system(
+    cars: Query<(&CarWheels, &ReadMassProperties)>,
+    mut wheels: Query<(&WheelSurface, &Velocity, &mut ExternalForce)>,
+) {
+    for (car_wheels, mass) in cars.iter() {
+        let load = mass.get().mass * 9.81 / car_wheels.entities.len() as f32;
+        for wheel in car_wheels.entities.iter() {
+            let Ok((wheel_surface, velocity, mut force)) = wheels.get_mut(*wheel) else {
+                continue;
+            };
+            let linvel = velocity.linvel.reject_from(Vec3::Y);
+            force.force = match wheel_surface.surface {
+                // fades in at walking speed so cars at rest don't jitter
+                Some(surface) => -linvel.clamp_length_max(1.) * surface.rolling_resistance() * load,
+                None => Vec3::ZERO,
+            };
+        }
+    }
+}
@@ -0,0 +1,192 @@
+use crate::{CarTrack, TrackConfig};
+use bevy::prelude::*;
+
+/// Spacing in meters of the lap trace used for the live delta.
+const LAP_TRACE_STEP: f32 = 10.;
+/// Driving back more than this invalidates the lap.
+const LAP_REVERSE_TOLERANCE: f32 = 10.;
+
+#[derive(Debug, Clone, Default)]
+pub struct LapTime {
+    pub time: f32,
+    pub sectors: Vec<f32>,
+    pub valid: bool,
+}
+
+#[derive(Component, Debug, Clone)]
+pub struct LapTimer {
+    pub current: f32,
+    /// Sector times of the current lap so far.
+    pub sectors: Vec<f32>,
+    pub last: Option<LapTime>,
+    pub best: Option<LapTime>,
+    /// Live difference to the best lap at the same point of the track.
+    pub delta: Option<f32>,
+    pub valid: bool,
+    pub laps: u32,
+    started: bool,
+    position: Option<f32>,
+    furthest: f32,
+    trace: Vec<f32>,
+    best_trace: Vec<f32>,
+}
+
+impl LapTimer {
+    /// Cars spawned away from the start line begin with an invalid out lap.
+    pub fn new(on_start_line: bool) -> Self {
+        Self {
+            current: 0.,
+            sectors: vec![],
+            last: None,
+            best: None,
+            delta: None,
+            valid: on_start_line,
+            laps: 0,
+            started: on_start_line,
+            position: None,
+            furthest: 0.,
+            trace: vec![],
+            best_trace: vec![],
+        }
+    }
+
+    /// Car moved elsewhere on the track, the lap running is invalid and the laps set are kept.
+    pub fn respawned(&mut self) {
+        self.valid = false;
+        self.position = None;
+    }
+
+    fn sector_time(&self) -> f32 {
+        self.current - self.sectors.iter().sum::<f32>()
+    }
+
+    fn restart(&mut self) {
+        self.current = 0.;
+        self.sectors.clear();
+        self.started = true;
+        self.valid = true;
+        self.furthest = 0.;
+        self.trace.clear();
+    }
+
+    /// Finishes the current lap, returns it and whether it is the new best.
+    fn complete(&mut self) -> (LapTime, bool) {
+        let mut sectors = std::mem::take(&mut self.sectors);
+        sectors.push(self.current - sectors.iter().sum::<f32>());
+        let lap = LapTime {
+            time: self.current,
+            sectors,
+            valid: self.valid,
+        };
+        let is_best = lap.valid && self.best.as_ref().is_none_or(|best| lap.time < best.time);
+        if is_best {
+            self.best = Some(lap.clone());
+            self.best_trace = std::mem::take(&mut self.trace);
+        }
+        self.last = Some(lap.clone());
+        self.laps += 1;
+        self.restart();
+        (lap, is_best)
+    }
+
+    fn best_time_at(&self, position: f32) -> Option<f32> {
+        let k = position / LAP_TRACE_STEP;
+        let (i, t) = (k.floor() as usize, k.fract());
+        match (self.best_trace.get(i), self.best_trace.get(i + 1)) {
+            (Some(a), Some(b)) => Some(a + (b - a) * t),
+            (Some(a), None) => Some(*a),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Message)]
+pub struct LapCompleted {
+    pub car: Entity,
+    pub index: usize,
+    pub lap: LapTime,
+    pub best: bool,
+}
+
+pub fn lap_timer_system(
+    time: Res<Time>,
+    track_config: Res<TrackConfig>,
+    mut cars: Query<(Entity, &CarTrack, &mut LapTimer)>,
+    mut lap_events: MessageWriter<LapCompleted>,
+) {
+    if !track_config.closed || track_config.track_length <= 0. {
+        return;
+    }
+    let track_length = track_config.track_length;
+    let half = track_length / 2.;
+    for (e, car_track, mut timer) in cars.iter_mut() {
+        let position = car_track.track_position;
+        let Some(prev) = timer.position.replace(position) else {
+            timer.furthest = if position > half {
+                position - track_length
+            } else {
+                position
+            };
+            continue;
+        };
+        timer.current += time.delta_secs();
+
+        if prev - position > half && (!timer.started || timer.furthest < half) {
+            // out lap after a spawn, the timed lap starts at the line
+            timer.restart();
+        } else if prev - position > half {
+            let (lap, best) = timer.complete();
+            debug!(
+                "car {} lap {:.2}s {:?}{}",
+                car_track.index,
+                lap.time,
+                lap.sectors,
+                if lap.valid { "" } else { " invalid" }
+            );
+            lap_events.write(LapCompleted {
+                car: e,
+                index: car_track.index,
+                lap,
+                best,
+            });
+        } else if position - prev > half {
+            // crossed the start line backwards
+            timer.valid = false;
+            timer.furthest = position;
+        }
+
+        let lap_position = if position - timer.furthest > half {
+            position - track_length
+        } else {
+            position
+        };
+        if lap_position > timer.furthest {
+            timer.furthest = lap_position;
+        } else if timer.furthest - lap_position > LAP_REVERSE_TOLERANCE {
+            timer.valid = false;
+        }
+
+        let sector = track_config
+            .sectors
+            .iter()
+            .filter(|b| **b <= position)
+            .count();
+        if sector > timer.sectors.len() && position - prev < half {
+            if sector > timer.sectors.len() + 1 {
+                timer.valid = false;
+            }
+            while timer.sectors.len() < sector {
+                let split = timer.sector_time();
+                timer.sectors.push(split);
+            }
+        }
+
+        while (timer.trace.len() as f32) * LAP_TRACE_STEP <= position.max(0.) {
+            let current = timer.current;
+            timer.trace.push(current);
+        }
+        timer.delta = timer
+            .best_time_at(position)
+            .map(|best| timer.current - best);
+    }
+}
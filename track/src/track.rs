@@ -1,5 +1,5 @@
+use crate::TrackAsset;
 use bevy::prelude::*;
-use std::f32::consts::FRAC_PI_2;
 
 // https://google.github.io/filament/Filament.html#materialsystem/parameterization/
 // https://google.github.io/filament/Material%20Properties.pdf
@@ -7,473 +7,133 @@ use std::f32::consts::FRAC_PI_2;
 #[derive(Component, Debug)]
 pub struct TrackRoad;
 
+#[derive(Component, Debug)]
+pub struct TrackEntity;
+
 #[derive(Component, Debug)]
 pub struct Track {
-    width: f32,
+    pub closed: bool,
+    pub width: Vec<f32>,
+    pub runoff_left: Vec<f32>,
+    pub runoff_right: Vec<f32>,
     pub points: Vec<Vec3>,
+    /// Signed horizontal curvature at every point, positive when turning left.
+    pub curvature: Vec<f32>,
     pub indices: Vec<u32>,
     pub collider_indices: Vec<[u32; 3]>,
     pub left: Vec<Vec3>,
     pub right: Vec<Vec3>,
     pub left_norm: Vec<Vec3>,
     pub right_norm: Vec<Vec3>,
+    pub normals: Vec<Vec3>,
 }
 
 impl Track {
     pub fn empty() -> Self {
         Track {
-            width: 5.,
+            closed: true,
+            width: Vec::new(),
+            runoff_left: Vec::new(),
+            runoff_right: Vec::new(),
             points: Vec::new(),
+            curvature: Vec::new(),
             indices: Vec::new(),
             collider_indices: Vec::new(),
             left: Vec::new(),
             right: Vec::new(),
             left_norm: Vec::new(),
             right_norm: Vec::new(),
+            normals: Vec::new(),
         }
     }
-    pub fn new() -> Self {
-        let positions = TRACK_POSITIONS;
+    pub fn new(asset: &TrackAsset) -> Self {
         let mut track = Track::empty();
-        let mut points: Vec<Vec3> = vec![];
-        points.extend(
-            positions
-                .iter()
-                .map(|pos| Vec3::new(pos.0, pos.1 + 0.001, pos.2))
-                .collect::<Vec<Vec3>>(),
-        );
-        track.points = points;
+        track.closed = !asset.open;
+        track.width = asset.widths();
+        track.runoff_left = asset.runoffs_left();
+        track.runoff_right = asset.runoffs_right();
+        let samples = asset.samples();
+        track.points = samples
+            .iter()
+            .map(|s| Vec3::new(s.position.x, s.position.y + 0.001, s.position.z))
+            .collect();
+        track.curvature = samples.iter().map(|s| s.curvature).collect();
+        let banking = asset.banking();
+        let points_len = track.points.len();
         for (i, point) in track.points.iter().enumerate() {
-            let last: bool = i + 1 == track.points.len();
+            let last: bool = i + 1 == points_len;
             let ix2: u32 = i as u32 * 2;
-            if last {
-                let inx = if last { 0 } else { i + 1 };
-                track.left_norm.push(track.left_norm[inx]);
-                track.right_norm.push(track.right_norm[inx]);
-                track.left.push(track.left[inx]);
-                track.right.push(track.right[inx]);
+            if last && track.closed {
+                track.left_norm.push(track.left_norm[0]);
+                track.right_norm.push(track.right_norm[0]);
+                track.left.push(track.left[0]);
+                track.right.push(track.right[0]);
+                track.normals.push(track.normals[0]);
             } else {
-                let (i1, i2) = ([ix2, ix2 + 1, ix2 + 2], [ix2 + 2, ix2 + 1, ix2 + 3]);
-                track.indices.extend(i1);
-                track.indices.extend(i2);
-                track.collider_indices.push(i1);
-                track.collider_indices.push(i2);
+                if !last {
+                    let (i1, i2) = ([ix2, ix2 + 1, ix2 + 2], [ix2 + 2, ix2 + 1, ix2 + 3]);
+                    track.indices.extend(i1);
+                    track.indices.extend(i2);
+                    track.collider_indices.push(i1);
+                    track.collider_indices.push(i2);
+                }
 
-                let point_prev = if i == 0 {
-                    track.points[track.points.len() - 1]
-                } else {
-                    track.points[i - 1]
-                };
-                let point_next = track.points[i + 1];
-                let vec_len = (point_prev - *point).length();
-                let angle = if vec_len == 0. {
-                    0.
-                } else {
-                    (*point - point_next).angle_between(point_prev - *point) / 2.
-                };
-                let dir: Vec3 = (point_next - *point).normalize();
-                let left_norm = Quat::from_rotation_y(FRAC_PI_2 + angle).mul_vec3(dir);
+                let tangent = samples[i].tangent;
+                let tangent_flat = Vec3::new(tangent.x, 0., tangent.z).normalize_or_zero();
+                let left_flat = Vec3::Y.cross(tangent_flat);
+                let left_norm = Quat::from_axis_angle(tangent, banking[i]).mul_vec3(left_flat);
                 let right_norm = -left_norm;
                 track.left_norm.push(left_norm);
                 track.right_norm.push(right_norm);
-                track.left.push(*point + left_norm * track.width);
-                track.right.push(*point + right_norm * track.width);
+                track.left.push(*point + left_norm * track.width[i]);
+                track.right.push(*point + right_norm * track.width[i]);
+                track
+                    .normals
+                    .push(tangent.cross(left_norm).normalize_or(Vec3::Y));
             }
         }
         track
     }
+    /// Whether a point lies on the road surface, seen from above.
+    pub fn road_contains(&self, p: Vec3, margin: f32) -> bool {
+        let p = p.xz();
+        self.points.windows(2).enumerate().any(|(i, w)| {
+            let (a, b) = (w[0].xz(), w[1].xz());
+            let ab = b - a;
+            let len_sq = ab.length_squared();
+            let t = if len_sq > 0. {
+                ((p - a).dot(ab) / len_sq).clamp(0., 1.)
+            } else {
+                0.
+            };
+            let width = self.width[i] + (self.width[i + 1] - self.width[i]) * t;
+            p.distance(a + ab * t) <= width + margin
+        })
+    }
+    pub fn wall_points(&self) -> (Vec<Vec3>, Vec<Vec3>) {
+        let mut left: Vec<Vec3> = vec![];
+        let mut right: Vec<Vec3> = vec![];
+        for (i, p) in self.points.iter().enumerate() {
+            left.push(*p + self.left_norm[i] * self.wall_offset_left(i));
+            right.push(*p + self.right_norm[i] * self.wall_offset_right(i));
+        }
+        (left, right)
+    }
+    pub fn wall_offset_left(&self, i: usize) -> f32 {
+        self.width[i] + self.runoff_left[i]
+    }
+    pub fn wall_offset_right(&self, i: usize) -> f32 {
+        self.width[i] + self.runoff_right[i]
+    }
     pub fn road(&self) -> (Vec<[f32; 3]>, Vec<[f32; 3]>) {
         let mut vertices: Vec<[f32; 3]> = vec![];
         let mut normals: Vec<[f32; 3]> = vec![];
         for (i, _) in self.points.iter().enumerate() {
             vertices.push(self.left[i].into());
             vertices.push(self.right[i].into());
-            normals.push(Vec3::Y.into());
-            normals.push(Vec3::Y.into());
+            normals.push(self.normals[i].into());
+            normals.push(self.normals[i].into());
         }
         return (vertices, normals);
     }
 }
-
-// fn face_normal(a: [f32; 3], b: [f32; 3], c: [f32; 3]) -> [f32; 3] {
-//     let (a, b, c) = (Vec3::from(a), Vec3::from(b), Vec3::from(c));
-//     (b - a).cross(c - a).normalize().into()
-// }
-
-pub const _TRACK_POSITIONS: [(f32, f32, f32, f32); 7] = [
-    (0., 0.0, 0., 1.0),
-    (100., 0.0, 0., 1.0),
-    (100., 0.0, 100., 1.0),
-    (0.0, 0.0, 100., 1.0),
-    (-100., 0.0, 100., 1.0),
-    (-100., 0.0, 0., 1.0),
-    (0., 0.0, 0., 1.0),
-];
-
-pub const TRACK_POSITIONS: [(f32, f32, f32, f32); 365] = [
-    (-65.2042, 0.0, 80.13815, 1.0),
-    (-115.01793, 0.0, 143.06631, 1.0),
-    (-166.25946, 0.0, 207.94211, 1.0),
-    (-217.50098, 0.0, 272.81802, 1.0),
-    (-265.31787, 0.0, 333.1974, 1.0),
-    (-278.88818, 0.0, 348.6136, 1.0),
-    (-289.03387, 0.0, 359.53333, 1.0),
-    (-299.1795, 0.0, 370.4531, 1.0),
-    (-309.71115, 0.0, 381.52063, 1.0),
-    (-325.6153, 0.0, 395.35187, 1.0),
-    (-357.13297, 0.0, 422.21912, 1.0),
-    (-389.7332, 0.0, 449.55347, 1.0),
-    (-408.00998, 0.0, 463.88077, 1.0),
-    (-414.2032, 0.0, 467.27847, 1.0),
-    (-419.8355, 0.0, 469.87085, 1.0),
-    (-424.9733, 0.0, 471.5963, 1.0),
-    (-428.5472, 0.0, 471.43433, 1.0),
-    (-430.55695, 0.0, 469.78018, 1.0),
-    (-431.86078, 0.0, 467.55853, 1.0),
-    (-432.82025, 0.0, 464.62982, 1.0),
-    (-433.69626, 0.0, 460.75327, 1.0),
-    (-434.31042, 0.0, 455.8055, 1.0),
-    (-434.37216, 0.0, 449.98566, 1.0),
-    (-433.95425, 0.0, 443.58792, 1.0),
-    (-433.15848, 0.0, 436.92114, 1.0),
-    (-431.98438, 0.0, 430.315, 1.0),
-    (-430.27872, 0.0, 423.49313, 1.0),
-    (-427.88867, 0.0, 415.7542, 1.0),
-    (-424.6275, 0.0, 406.36566, 1.0),
-    (-420.02145, 0.0, 394.77567, 1.0),
-    (-414.31296, 0.0, 381.75626, 1.0),
-    (-408.50928, 0.0, 368.77945, 1.0),
-    (-403.61978, 0.0, 357.3127, 1.0),
-    (-399.92276, 0.0, 348.08023, 1.0),
-    (-397.03543, 0.0, 340.52264, 1.0),
-    (-394.86655, 0.0, 333.9693, 1.0),
-    (-393.35565, 0.0, 327.80286, 1.0),
-    (-392.39914, 0.0, 321.95062, 1.0),
-    (-391.93256, 0.0, 316.53302, 1.0),
-    (-391.9601, 0.0, 311.25452, 1.0),
-    (-392.49002, 0.0, 305.79987, 1.0),
-    (-393.51434, 0.0, 300.11154, 1.0),
-    (-395.09988, 0.0, 294.37674, 1.0),
-    (-397.3798, 0.0, 288.68988, 1.0),
-    (-400.48355, 0.0, 283.13736, 1.0),
-    (-404.43283, 0.0, 277.85242, 1.0),
-    (-409.10828, 0.0, 273.01215, 1.0),
-    (-414.40457, 0.0, 268.77515, 1.0),
-    (-420.1761, 0.0, 265.2932, 1.0),
-    (-425.88422, 0.0, 262.65134, 1.0),
-    (-431.39847, 0.0, 260.7731, 1.0),
-    (-437.2533, 0.0, 259.54214, 1.0),
-    (-443.98825, 0.0, 258.85468, 1.0),
-    (-451.57062, 0.0, 258.7662, 1.0),
-    (-460.00357, 0.0, 259.4374, 1.0),
-    (-469.8863, 0.0, 260.9392, 1.0),
-    (-481.91312, 0.0, 263.39816, 1.0),
-    (-496.97186, 0.0, 267.5356, 1.0),
-    (-514.17163, 0.0, 273.38562, 1.0),
-    (-531.24316, 0.0, 279.93073, 1.0),
-    (-545.9178, 0.0, 286.0777, 1.0),
-    (-557.31055, 0.0, 291.0736, 1.0),
-    (-566.29144, 0.0, 295.29834, 1.0),
-    (-573.51685, 0.0, 299.5471, 1.0),
-    (-579.5647, 0.0, 304.574, 1.0),
-    (-584.5794, 0.0, 310.3618, 1.0),
-    (-588.42865, 0.0, 316.41418, 1.0),
-    (-591.2291, 0.0, 322.6873, 1.0),
-    (-593.12213, 0.0, 329.17633, 1.0),
-    (-594.20825, 0.0, 335.76184, 1.0),
-    (-594.4774, 0.0, 342.1986, 1.0),
-    (-593.88605, 0.0, 348.27322, 1.0),
-    (-592.4117, 0.0, 353.7739, 1.0),
-    (-590.2653, 0.0, 358.48477, 1.0),
-    (-587.6792, 0.0, 362.38956, 1.0),
-    (-584.6661, 0.0, 365.61047, 1.0),
-    (-581.2511, 0.0, 368.2584, 1.0),
-    (-577.7637, 0.0, 370.22656, 1.0),
-    (-574.08264, 0.0, 371.52832, 1.0),
-    (-569.4827, 0.0, 372.47562, 1.0),
-    (-563.0689, 0.0, 373.35736, 1.0),
-    (-553.0936, 0.0, 373.9443, 1.0),
-    (-540.82477, 0.0, 374.0006, 1.0),
-    (-530.3922, 0.0, 373.80402, 1.0),
-    (-525.5116, 0.0, 373.67676, 1.0),
-    (-523.04724, 0.0, 373.86285, 1.0),
-    (-518.47345, 0.0, 374.49692, 1.0),
-    (-513.189, 0.0, 375.72046, 1.0),
-    (-509.06354, 0.0, 377.64725, 1.0),
-    (-506.4927, 0.0, 380.10782, 1.0),
-    (-504.57764, 0.0, 382.95535, 1.0),
-    (-503.0304, 0.0, 386.3425, 1.0),
-    (-501.70184, 0.0, 390.71503, 1.0),
-    (-501.07376, 0.0, 398.86667, 1.0),
-    (-501.36905, 0.0, 410.53516, 1.0),
-    (-502.0071, 0.0, 421.0746, 1.0),
-    (-502.56863, 0.0, 427.98648, 1.0),
-    (-504.91052, 0.0, 452.9993, 1.0),
-    (-509.68292, 0.0, 503.84277, 1.0),
-    (-514.4554, 0.0, 554.6865, 1.0),
-    (-516.7904, 0.0, 579.5851, 1.0),
-    (-517.29285, 0.0, 585.1606, 1.0),
-    (-518.0862, 0.0, 593.61584, 1.0),
-    (-519.2647, 0.0, 604.97363, 1.0),
-    (-520.74713, 0.0, 617.04456, 1.0),
-    (-522.8299, 0.0, 629.1848, 1.0),
-    (-525.3593, 0.0, 640.69836, 1.0),
-    (-527.50366, 0.0, 649.3096, 1.0),
-    (-528.9951, 0.0, 655.11456, 1.0),
-    (-535.4711, 0.0, 681.8989, 1.0),
-    (-548.6861, 0.0, 736.62506, 1.0),
-    (-561.9011, 0.0, 791.35114, 1.0),
-    (-568.3536, 0.0, 818.3286, 1.0),
-    (-569.53, 0.0, 826.4492, 1.0),
-    (-570.70795, 0.0, 839.3703, 1.0),
-    (-571.44257, 0.0, 853.9521, 1.0),
-    (-570.78217, 0.0, 864.7146, 1.0),
-    (-568.8492, 0.0, 871.31995, 1.0),
-    (-566.3807, 0.0, 877.0248, 1.0),
-    (-563.4499, 0.0, 882.33984, 1.0),
-    (-559.89966, 0.0, 887.50604, 1.0),
-    (-554.165, 0.0, 893.0686, 1.0),
-    (-546.4851, 0.0, 898.6526, 1.0),
-    (-539.71094, 0.0, 902.9658, 1.0),
-    (-535.73755, 0.0, 905.2305, 1.0),
-    (-524.98145, 0.0, 910.3941, 1.0),
-    (-503.28568, 0.0, 920.7534, 1.0),
-    (-481.59003, 0.0, 931.1128, 1.0),
-    (-470.85144, 0.0, 936.24805, 1.0),
-    (-467.09808, 0.0, 938.1513, 1.0),
-    (-460.84183, 0.0, 941.55023, 1.0),
-    (-453.8461, 0.0, 945.77936, 1.0),
-    (-448.8296, 0.0, 949.66534, 1.0),
-    (-446.10443, 0.0, 952.7011, 1.0),
-    (-444.12866, 0.0, 955.3935, 1.0),
-    (-442.5293, 0.0, 958.25934, 1.0),
-    (-441.08817, 0.0, 961.7788, 1.0),
-    (-439.8083, 0.0, 966.1127, 1.0),
-    (-438.90985, 0.0, 970.9869, 1.0),
-    (-438.5368, 0.0, 976.15826, 1.0),
-    (-438.81223, 0.0, 981.3528, 1.0),
-    (-439.745, 0.0, 986.0166, 1.0),
-    (-441.30478, 0.0, 990.5505, 1.0),
-    (-443.37558, 0.0, 994.8909, 1.0),
-    (-446.07837, 0.0, 999.1318, 1.0),
-    (-451.66498, 0.0, 1005.1821, 1.0),
-    (-459.28683, 0.0, 1011.8669, 1.0),
-    (-466.0256, 0.0, 1017.2575, 1.0),
-    (-470.2183, 0.0, 1020.36884, 1.0),
-    (-483.80643, 0.0, 1029.5912, 1.0),
-    (-511.35403, 0.0, 1048.2463, 1.0),
-    (-538.90155, 0.0, 1066.9016, 1.0),
-    (-552.80426, 0.0, 1076.3949, 1.0),
-    (-560.91974, 0.0, 1082.88, 1.0),
-    (-576.75183, 0.0, 1096.0057, 1.0),
-    (-596.0649, 0.0, 1112.4362, 1.0),
-    (-613.2298, 0.0, 1127.83, 1.0),
-    (-627.2941, 0.0, 1141.3761, 1.0),
-    (-640.83124, 0.0, 1155.0972, 1.0),
-    (-654.0887, 0.0, 1169.3721, 1.0),
-    (-667.04944, 0.0, 1184.3892, 1.0),
-    (-679.70856, 0.0, 1200.3035, 1.0),
-    (-691.9722, 0.0, 1216.924, 1.0),
-    (-703.6771, 0.0, 1233.86, 1.0),
-    (-714.66284, 0.0, 1250.7272, 1.0),
-    (-724.8737, 0.0, 1267.3837, 1.0),
-    (-734.3853, 0.0, 1283.8623, 1.0),
-    (-743.25543, 0.0, 1300.0703, 1.0),
-    (-751.5354, 0.0, 1315.9341, 1.0),
-    (-759.2372, 0.0, 1331.6665, 1.0),
-    (-766.3371, 0.0, 1346.9092, 1.0),
-    (-772.8273, 0.0, 1360.6372, 1.0),
-    (-778.65405, 0.0, 1371.7915, 1.0),
-    (-783.33075, 0.0, 1379.703, 1.0),
-    (-786.7993, 0.0, 1384.9735, 1.0),
-    (-789.7208, 0.0, 1388.6624, 1.0),
-    (-792.76086, 0.0, 1391.7944, 1.0),
-    (-795.9293, 0.0, 1394.6609, 1.0),
-    (-798.98456, 0.0, 1396.9653, 1.0),
-    (-802.1735, 0.0, 1398.753, 1.0),
-    (-805.784, 0.0, 1400.1136, 1.0),
-    (-809.96893, 0.0, 1401.1653, 1.0),
-    (-814.5014, 0.0, 1401.8734, 1.0),
-    (-819.037, 0.0, 1402.0732, 1.0),
-    (-823.2341, 0.0, 1401.6229, 1.0),
-    (-826.8905, 0.0, 1400.7245, 1.0),
-    (-830.12506, 0.0, 1399.5732, 1.0),
-    (-833.1312, 0.0, 1398.0159, 1.0),
-    (-836.0986, 0.0, 1395.8983, 1.0),
-    (-839.118, 0.0, 1393.4063, 1.0),
-    (-842.0764, 0.0, 1390.6963, 1.0),
-    (-844.8236, 0.0, 1387.5676, 1.0),
-    (-847.2217, 0.0, 1383.788, 1.0),
-    (-849.27783, 0.0, 1379.2133, 1.0),
-    (-851.028, 0.0, 1374.1077, 1.0),
-    (-852.3827, 0.0, 1368.92, 1.0),
-    (-853.2362, 0.0, 1364.0802, 1.0),
-    (-853.46545, 0.0, 1359.6475, 1.0),
-    (-853.1126, 0.0, 1355.3767, 1.0),
-    (-852.34705, 0.0, 1351.1876, 1.0),
-    (-851.3286, 0.0, 1347.0225, 1.0),
-    (-850.00476, 0.0, 1342.8528, 1.0),
-    (-848.2704, 0.0, 1338.7137, 1.0),
-    (-846.19745, 0.0, 1334.6536, 1.0),
-    (-843.8758, 0.0, 1330.7296, 1.0),
-    (-841.37744, 0.0, 1327.0695, 1.0),
-    (-838.62317, 0.0, 1323.5676, 1.0),
-    (-835.451, 0.0, 1319.8938, 1.0),
-    (-831.61707, 0.0, 1315.6149, 1.0),
-    (-826.18005, 0.0, 1309.543, 1.0),
-    (-818.7661, 0.0, 1301.2446, 1.0),
-    (-810.07666, 0.0, 1291.5431, 1.0),
-    (-799.6235, 0.0, 1279.7172, 1.0),
-    (-775.042, 0.0, 1249.7529, 1.0),
-    (-734.94806, 0.0, 1199.6663, 1.0),
-    (-697.1531, 0.0, 1152.1194, 1.0),
-    (-679.0134, 0.0, 1129.2075, 1.0),
-    (-674.2861, 0.0, 1122.9059, 1.0),
-    (-667.31836, 0.0, 1113.1593, 1.0),
-    (-659.7833, 0.0, 1102.012, 1.0),
-    (-654.88367, 0.0, 1093.467, 1.0),
-    (-652.69336, 0.0, 1087.6216, 1.0),
-    (-651.18933, 0.0, 1081.9497, 1.0),
-    (-650.0792, 0.0, 1076.083, 1.0),
-    (-649.2716, 0.0, 1069.916, 1.0),
-    (-648.7646, 0.0, 1063.5455, 1.0),
-    (-648.66394, 0.0, 1056.8918, 1.0),
-    (-649.05786, 0.0, 1049.5547, 1.0),
-    (-650.05646, 0.0, 1041.1195, 1.0),
-    (-651.9869, 0.0, 1031.388, 1.0),
-    (-654.8272, 0.0, 1020.7129, 1.0),
-    (-658.1062, 0.0, 1009.59924, 1.0),
-    (-661.3561, 0.0, 998.52045, 1.0),
-    (-664.5649, 0.0, 987.541, 1.0),
-    (-667.7342, 0.0, 976.7097, 1.0),
-    (-670.41895, 0.0, 966.4733, 1.0),
-    (-672.1706, 0.0, 957.3218, 1.0),
-    (-672.98773, 0.0, 949.6955, 1.0),
-    (-673.22833, 0.0, 943.28, 1.0),
-    (-673.0429, 0.0, 937.306, 1.0),
-    (-672.43585, 0.0, 930.53937, 1.0),
-    (-670.29205, 0.0, 917.975, 1.0),
-    (-666.54596, 0.0, 899.75165, 1.0),
-    (-662.9507, 0.0, 883.20966, 1.0),
-    (-660.2756, 0.0, 871.6004, 1.0),
-    (-648.39417, 0.0, 823.2599, 1.0),
-    (-624.14136, 0.0, 724.70264, 1.0),
-    (-599.8886, 0.0, 626.1455, 1.0),
-    (-588.01685, 0.0, 577.862, 1.0),
-    (-585.4699, 0.0, 566.96313, 1.0),
-    (-582.2554, 0.0, 552.0473, 1.0),
-    (-579.19385, 0.0, 535.8664, 1.0),
-    (-578.07904, 0.0, 525.20807, 1.0),
-    (-578.8495, 0.0, 519.93085, 1.0),
-    (-580.2771, 0.0, 515.5043, 1.0),
-    (-582.21106, 0.0, 511.40204, 1.0),
-    (-584.7895, 0.0, 507.4692, 1.0),
-    (-589.679, 0.0, 502.93228, 1.0),
-    (-596.7556, 0.0, 497.94452, 1.0),
-    (-603.1727, 0.0, 493.8976, 1.0),
-    (-607.4722, 0.0, 491.5452, 1.0),
-    (-623.7511, 0.0, 484.48615, 1.0),
-    (-656.87476, 0.0, 470.202, 1.0),
-    (-689.99835, 0.0, 455.91797, 1.0),
-    (-706.3221, 0.0, 448.8444, 1.0),
-    (-711.1773, 0.0, 446.30872, 1.0),
-    (-718.8409, 0.0, 441.82343, 1.0),
-    (-727.42194, 0.0, 436.23907, 1.0),
-    (-733.6259, 0.0, 431.07263, 1.0),
-    (-737.3321, 0.0, 426.6945, 1.0),
-    (-740.4598, 0.0, 422.38446, 1.0),
-    (-743.11456, 0.0, 417.84235, 1.0),
-    (-745.196, 0.0, 412.88284, 1.0),
-    (-746.5815, 0.0, 407.92957, 1.0),
-    (-747.42017, 0.0, 403.1585, 1.0),
-    (-748.0648, 0.0, 397.97037, 1.0),
-    (-748.52356, 0.0, 391.3672, 1.0),
-    (-745.4203, 0.0, 379.53638, 1.0),
-    (-737.8833, 0.0, 363.08496, 1.0),
-    (-730.0958, 0.0, 348.38242, 1.0),
-    (-723.9885, 0.0, 338.31982, 1.0),
-    (-695.7259, 0.0, 297.90765, 1.0),
-    (-637.99475, 0.0, 215.57397, 1.0),
-    (-580.26355, 0.0, 133.23997, 1.0),
-    (-551.943, 0.0, 92.91128, 1.0),
-    (-545.06177, 0.0, 83.881294, 1.0),
-    (-534.8899, 0.0, 71.46597, 1.0),
-    (-522.9068, 0.0, 57.708076, 1.0),
-    (-512.61237, 0.0, 47.89148, 1.0),
-    (-500.06433, 0.0, 40.53226, 1.0),
-    (-483.0107, 0.0, 32.302956, 1.0),
-    (-467.7683, 0.0, 25.416548, 1.0),
-    (-456.79745, 0.0, 20.844793, 1.0),
-    (-408.33072, 0.0, 2.470601, 1.0),
-    (-309.40173, 0.0, -34.967377, 1.0),
-    (-210.47275, 0.0, -72.40536, 1.0),
-    (-162.10571, 0.0, -90.71279, 1.0),
-    (-152.39862, 0.0, -94.43996, 1.0),
-    (-140.31851, 0.0, -99.21548, 1.0),
-    (-127.875374, 0.0, -104.37261, 1.0),
-    (-121.05636, 0.0, -107.71647, 1.0),
-    (-119.434525, 0.0, -109.259224, 1.0),
-    (-118.65479, 0.0, -110.55949, 1.0),
-    (-118.15833, 0.0, -111.999504, 1.0),
-    (-117.83322, 0.0, -113.86208, 1.0),
-    (-117.85459, 0.0, -116.677376, 1.0),
-    (-118.27408, 0.0, -120.11793, 1.0),
-    (-118.77339, 0.0, -123.03669, 1.0),
-    (-119.06937, 0.0, -124.4875, 1.0),
-    (-119.57418, 0.0, -126.25783, 1.0),
-    (-120.58323, 0.0, -130.02583, 1.0),
-    (-121.619156, 0.0, -134.66245, 1.0),
-    (-122.172325, 0.0, -138.8275, 1.0),
-    (-122.19422, 0.0, -141.96877, 1.0),
-    (-121.85974, 0.0, -144.6154, 1.0),
-    (-121.03129, 0.0, -147.23032, 1.0),
-    (-119.55593, 0.0, -150.18484, 1.0),
-    (-117.444885, 0.0, -153.11598, 1.0),
-    (-114.55189, 0.0, -155.78104, 1.0),
-    (-110.46152, 0.0, -158.75069, 1.0),
-    (-104.697464, 0.0, -162.64195, 1.0),
-    (-96.547935, 0.0, -167.65044, 1.0),
-    (-86.56804, 0.0, -173.1839, 1.0),
-    (-76.39321, 0.0, -178.54279, 1.0),
-    (-67.69057, 0.0, -183.05646, 1.0),
-    (-61.315372, 0.0, -186.43925, 1.0),
-    (-56.309635, 0.0, -189.15627, 1.0),
-    (-51.31945, 0.0, -191.78577, 1.0),
-    (-44.80492, 0.0, -194.95728, 1.0),
-    (-33.965153, 0.0, -199.6233, 1.0),
-    (-19.940641, 0.0, -205.2524, 1.0),
-    (-7.759984, 0.0, -210.00179, 1.0),
-    (-1.124318, 0.0, -212.48625, 1.0),
-    (12.489059, 0.0, -216.89825, 1.0),
-    (39.678364, 0.0, -225.65486, 1.0),
-    (66.867455, 0.0, -234.41145, 1.0),
-    (80.31314, 0.0, -238.69998, 1.0),
-    (84.88849, 0.0, -239.63683, 1.0),
-    (92.63419, 0.0, -240.71968, 1.0),
-    (101.754, 0.0, -241.44905, 1.0),
-    (109.249596, 0.0, -240.98724, 1.0),
-    (114.79173, 0.0, -239.38567, 1.0),
-    (119.82243, 0.0, -237.22177, 1.0),
-    (124.29548, 0.0, -234.53355, 1.0),
-    (128.02698, 0.0, -231.32362, 1.0),
-    (131.12634, 0.0, -227.82646, 1.0),
-    (133.8187, 0.0, -224.23863, 1.0),
-    (136.1124, 0.0, -220.4993, 1.0),
-    (137.99931, 0.0, -216.53261, 1.0),
-    (139.53043, 0.0, -212.36807, 1.0),
-    (140.75928, 0.0, -208.00507, 1.0),
-    (141.68025, 0.0, -203.3181, 1.0),
-    (142.27525, 0.0, -198.20486, 1.0),
-    (142.45554, 0.0, -192.92119, 1.0),
-    (142.18015, 0.0, -187.79083, 1.0),
-    (141.51033, 0.0, -182.82297, 1.0),
-    (140.27577, 0.0, -177.71089, 1.0),
-    (136.10605, 0.0, -169.56802, 1.0),
-    (128.7902, 0.0, -158.28867, 1.0),
-    (121.75907, 0.0, -148.20049, 1.0),
-    (116.03888, 0.0, -140.65422, 1.0),
-    (86.867874, 0.0, -105.11299, 1.0),
-    (27.652275, 0.0, -33.059837, 1.0),
-    (-27.773415, 0.0, 34.381996, 1.0),
-    (-65.2042, 0.0, 80.13815, 1.0),
-];
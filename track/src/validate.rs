@@ -0,0 +1,290 @@
+use crate::{TrackAsset, TrackDirection};
+use bevy::prelude::*;
+use std::collections::HashSet;
+use std::f32::consts::FRAC_PI_2;
+use thiserror::Error;
+
+/// Control points closer than this leave the spline direction undefined.
+const VALIDATE_MIN_SEGMENT: f32 = 0.5;
+/// Turns sharper than this between control point chords fold the spline back on itself.
+const VALIDATE_MAX_HEADING_JUMP: f32 = FRAC_PI_2;
+/// Parts of the track further apart than this vertically pass over each other on a bridge.
+const VALIDATE_BRIDGE_CLEARANCE: f32 = 4.;
+
+/// Geometry problem, indices refer to the track control points.
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum TrackIssue {
+    #[error("points {a} and {b}: centerline crosses itself")]
+    SelfIntersection { a: usize, b: usize },
+    #[error("point {index}: radius {radius:.1}m is below the half width {width:.1}m, road edges overlap")]
+    EdgesOverlap {
+        index: usize,
+        radius: f32,
+        width: f32,
+    },
+    #[error("point {index}: {length:.2}m to the next point is too short")]
+    ShortSegment { index: usize, length: f32 },
+    #[error("point {index}: heading jumps {degrees:.0} degrees")]
+    HeadingJump { index: usize, degrees: f32 },
+    #[error(
+        "point {index}: radius {radius:.1}m folds the inner wall {offset:.1}m off the centerline"
+    )]
+    WallFold {
+        index: usize,
+        radius: f32,
+        offset: f32,
+    },
+    #[error("points {a} and {b}: centerlines {distance:.1}m apart, walls need {required:.1}m")]
+    WallClearance {
+        a: usize,
+        b: usize,
+        distance: f32,
+        required: f32,
+    },
+}
+
+impl TrackIssue {
+    fn key(&self) -> (u8, usize, usize) {
+        match *self {
+            TrackIssue::SelfIntersection { a, b } => (0, a, b),
+            TrackIssue::EdgesOverlap { index, .. } => (1, index, 0),
+            TrackIssue::ShortSegment { index, .. } => (2, index, 0),
+            TrackIssue::HeadingJump { index, .. } => (3, index, 0),
+            TrackIssue::WallFold { index, .. } => (4, index, 0),
+            TrackIssue::WallClearance { a, b, .. } => (5, a, b),
+        }
+    }
+}
+
+/// Checks the track geometry before it is built, one issue per problem and control point.
+pub fn validate_track(asset: &TrackAsset) -> Vec<TrackIssue> {
+    let points: Vec<Vec3> = asset.points.iter().map(|p| p.position.into()).collect();
+    let n = points.len();
+    if n < 2 {
+        return vec![];
+    }
+    let mut issues: Vec<TrackIssue> = vec![];
+    let mut keys: HashSet<(u8, usize, usize)> = HashSet::new();
+    let mut report = |issue: TrackIssue| {
+        if keys.insert(issue.key()) {
+            issues.push(issue);
+        }
+    };
+
+    let chord = |i: usize| points[(i + 1) % n] - points[i];
+    let (chords, corners) = match asset.open {
+        true => (0..n - 1, 1..n - 1),
+        false => (0..n, 0..n),
+    };
+    for i in chords {
+        let length = chord(i).length();
+        if length < VALIDATE_MIN_SEGMENT {
+            report(TrackIssue::ShortSegment { index: i, length });
+        }
+    }
+    for i in corners {
+        let (a, b) = (chord((i + n - 1) % n).xz(), chord(i).xz());
+        if a.length() < VALIDATE_MIN_SEGMENT || b.length() < VALIDATE_MIN_SEGMENT {
+            continue;
+        }
+        let angle = a.angle_to(b).abs();
+        if angle > VALIDATE_MAX_HEADING_JUMP {
+            report(TrackIssue::HeadingJump {
+                index: i,
+                degrees: angle.to_degrees(),
+            });
+        }
+    }
+
+    let samples = asset.samples();
+    let widths = asset.widths();
+    let (runoffs_left, runoffs_right) = (asset.runoffs_left(), asset.runoffs_right());
+    // samples follow the driving direction, reverse tracks run the points backwards
+    let point_index = |span: usize| match asset.direction {
+        TrackDirection::Forward => span % n,
+        TrackDirection::Reverse => n - 1 - span % n,
+    };
+    for (i, s) in samples.iter().enumerate() {
+        let radius = 1. / s.curvature.abs();
+        let index = point_index(s.span);
+        if radius < widths[i] {
+            report(TrackIssue::EdgesOverlap {
+                index,
+                radius,
+                width: widths[i],
+            });
+        }
+        let runoff = match s.curvature > 0. {
+            true => runoffs_left[i],
+            false => runoffs_right[i],
+        };
+        if radius < widths[i] + runoff {
+            report(TrackIssue::WallFold {
+                index,
+                radius,
+                offset: widths[i] + runoff,
+            });
+        }
+    }
+
+    let mut distances: Vec<f32> = vec![0.];
+    for w in samples.windows(2) {
+        distances.push(distances.last().unwrap() + w[0].position.distance(w[1].position));
+    }
+    let length = distances.last().copied().unwrap_or(0.);
+    // road edge plus run-off towards the other point
+    let offset_towards = |i: usize, p: Vec3| {
+        let left = Vec3::Y.cross(samples[i].tangent).xz();
+        match (p - samples[i].position).xz().dot(left) > 0. {
+            true => widths[i] + runoffs_left[i],
+            false => widths[i] + runoffs_right[i],
+        }
+    };
+    for i in 0..samples.len().saturating_sub(1) {
+        for j in i + 2..samples.len() - 1 {
+            let (a, b) = (samples[i].position, samples[j].position);
+            if (a.y - b.y).abs() > VALIDATE_BRIDGE_CLEARANCE {
+                continue;
+            }
+            let along = match asset.open {
+                true => distances[j] - distances[i],
+                false => (distances[j] - distances[i]).min(length - distances[j] + distances[i]),
+            };
+            let required = offset_towards(i, b) + offset_towards(j, a);
+            if along < 2. * required {
+                continue;
+            }
+            let (ia, ib) = (point_index(samples[i].span), point_index(samples[j].span));
+            if segments_cross(
+                a.xz(),
+                samples[i + 1].position.xz(),
+                b.xz(),
+                samples[j + 1].position.xz(),
+            ) {
+                report(TrackIssue::SelfIntersection { a: ia, b: ib });
+                continue;
+            }
+            let distance = a.xz().distance(b.xz());
+            if distance < required {
+                report(TrackIssue::WallClearance {
+                    a: ia,
+                    b: ib,
+                    distance,
+                    required,
+                });
+            }
+        }
+    }
+    issues
+}
+
+fn segments_cross(a1: Vec2, a2: Vec2, b1: Vec2, b2: Vec2) -> bool {
+    let (da, db) = (a2 - a1, b2 - b1);
+    let denominator = da.perp_dot(db);
+    if denominator == 0. {
+        return false;
+    }
+    let t = (b1 - a1).perp_dot(db) / denominator;
+    let u = (b1 - a1).perp_dot(da) / denominator;
+    (0. ..=1.).contains(&t) && (0. ..=1.).contains(&u)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TrackPoint;
+    use std::f32::consts::{PI, TAU};
+
+    fn asset(points: Vec<Vec2>, open: bool) -> TrackAsset {
+        TrackAsset {
+            name: "test".to_string(),
+            width: 5.,
+            runoff: 2.5,
+            start: [points[0].x, 0., points[0].y],
+            open,
+            finish: None,
+            sectors: vec![],
+            direction: TrackDirection::Forward,
+            points: points
+                .iter()
+                .map(|p| TrackPoint::new([p.x, 0., p.y]))
+                .collect(),
+            branches: vec![],
+            surfaces: vec![],
+            kerbs: default(),
+            scenery: vec![],
+            scatter: vec![],
+            barriers: vec![],
+            session: None,
+        }
+    }
+
+    fn circle(radius: f32, n: usize) -> Vec<Vec2> {
+        (0..n)
+            .map(|i| Vec2::from_angle(TAU * i as f32 / n as f32) * radius)
+            .collect()
+    }
+
+    #[test]
+    fn circle_is_valid() {
+        assert_eq!(validate_track(&asset(circle(100., 32), false)), vec![]);
+    }
+
+    #[test]
+    fn figure_eight_crosses_itself() {
+        let points = (0..48)
+            .map(|i| {
+                let t = TAU * i as f32 / 48.;
+                Vec2::new(200. * t.sin(), 100. * (2. * t).sin())
+            })
+            .collect();
+        let issues = validate_track(&asset(points, false));
+        assert!(issues
+            .iter()
+            .any(|issue| matches!(issue, TrackIssue::SelfIntersection { .. })));
+    }
+
+    #[test]
+    fn duplicate_points_are_short_segments() {
+        let mut points = circle(100., 32);
+        points.insert(5, points[5]);
+        let issues = validate_track(&asset(points, false));
+        assert!(issues
+            .iter()
+            .any(|issue| matches!(issue, TrackIssue::ShortSegment { index: 5, .. })));
+        // the zero length chord must not turn into NaN radii or headings
+        assert!(issues
+            .iter()
+            .all(|issue| !issue.to_string().contains("NaN")));
+    }
+
+    #[test]
+    fn hairpin_tighter_than_half_width_overlaps() {
+        let radius = 3.;
+        let mut points: Vec<Vec2> = (0..5).map(|i| Vec2::new(0., i as f32 * 10.)).collect();
+        let top = Vec2::new(radius, 40.);
+        points.extend((1..8).map(|i| {
+            let angle = PI * i as f32 / 8.;
+            top + Vec2::new(-angle.cos(), angle.sin()) * radius
+        }));
+        points.extend((0..5).map(|i| Vec2::new(2. * radius, 40. - i as f32 * 10.)));
+        let issues = validate_track(&asset(points, true));
+        assert!(issues.iter().any(
+            |issue| matches!(issue, TrackIssue::EdgesOverlap { radius, width, .. } if radius < width)
+        ));
+    }
+
+    #[test]
+    fn shipped_tracks_are_valid() {
+        let options = ron::Options::default()
+            .with_default_extension(ron::extensions::Extensions::IMPLICIT_SOME);
+        for name in ["default", "oval", "hillclimb"] {
+            let path = format!(
+                "{}/../assets/tracks/{name}.track.ron",
+                env!("CARGO_MANIFEST_DIR")
+            );
+            let asset: TrackAsset = options.from_bytes(&std::fs::read(path).unwrap()).unwrap();
+            assert_eq!(validate_track(&asset), vec![], "{name}");
+        }
+    }
+}
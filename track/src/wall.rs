@@ -1,25 +1,176 @@
 use crate::material::MaterialHandle;
+use crate::{Surface, Track, TrackEntity};
 use bevy::asset::RenderAssetUsages;
 use bevy::mesh::{Indices, PrimitiveTopology, VertexAttributeValues};
 use bevy::prelude::*;
 use bevy_garage_car::STATIC_GROUP;
 use bevy_rapier3d::{na::Point3, prelude::Real, prelude::*, rapier::prelude::ColliderShape};
+use serde::{Deserialize, Serialize};
 use std::ops::{Mul, Sub};
 
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BarrierKind {
+    #[default]
+    Concrete,
+    /// Steel guard rail that springs cars back a little.
+    Armco,
+    /// Loose tire stacks that soak up the impact.
+    TireWall,
+    /// Heavy foam blocks, softer than tires and they don't bounce.
+    TecPro,
+    /// Tall fence that keeps cars and debris out of the crowd.
+    CatchFence,
+    /// No barrier, leaves a gap for a pit entry or run-off area.
+    Open,
+}
+
+impl BarrierKind {
+    /// Every barrier that gets built, all but `Open`.
+    pub const BUILT: [BarrierKind; 5] = [
+        BarrierKind::Concrete,
+        BarrierKind::Armco,
+        BarrierKind::TireWall,
+        BarrierKind::TecPro,
+        BarrierKind::CatchFence,
+    ];
+    /// Thickness and height in meters.
+    pub fn size(self) -> (f32, f32) {
+        match self {
+            BarrierKind::Concrete => (0.1, 0.6),
+            BarrierKind::Armco => (0.3, 0.75),
+            BarrierKind::TireWall => (0.7, 1.2),
+            BarrierKind::TecPro => (0.8, 1.),
+            BarrierKind::CatchFence => (0.05, 3.5),
+            BarrierKind::Open => (0., 0.),
+        }
+    }
+    pub fn friction(self) -> f32 {
+        match self {
+            BarrierKind::Concrete | BarrierKind::Open => 0.1,
+            BarrierKind::Armco => 0.2,
+            BarrierKind::TireWall => 0.8,
+            BarrierKind::TecPro => 0.6,
+            BarrierKind::CatchFence => 0.5,
+        }
+    }
+    pub fn restitution(self) -> f32 {
+        match self {
+            BarrierKind::Armco => 0.3,
+            BarrierKind::TireWall => 0.1,
+            BarrierKind::CatchFence => 0.05,
+            BarrierKind::Concrete | BarrierKind::TecPro | BarrierKind::Open => 0.,
+        }
+    }
+    /// Soft barriers are built from loose bodies the car pushes around.
+    pub fn is_soft(self) -> bool {
+        matches!(self, BarrierKind::TireWall | BarrierKind::TecPro)
+    }
+    fn material(self, handled_materials: &MaterialHandle) -> Handle<StandardMaterial> {
+        match self {
+            BarrierKind::Concrete | BarrierKind::Open => handled_materials.wall.clone(),
+            BarrierKind::Armco => handled_materials.armco.clone(),
+            BarrierKind::TireWall => handled_materials.tire_wall.clone(),
+            BarrierKind::TecPro => handled_materials.tecpro.clone(),
+            BarrierKind::CatchFence => handled_materials.catch_fence.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BarrierSide {
+    Left,
+    Right,
+    #[default]
+    Both,
+}
+
+/// Barrier between two distances from the start line, concrete everywhere else.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct BarrierZone {
+    pub from: f32,
+    pub to: f32,
+    #[serde(default)]
+    pub side: BarrierSide,
+    pub kind: BarrierKind,
+}
+
+/// Barrier kind of every wall segment on one side of the road.
+pub fn barrier_kinds(
+    track: &Track,
+    start: Vec3,
+    zones: &[BarrierZone],
+    left: bool,
+) -> Vec<BarrierKind> {
+    let meters = track_meters(track, start);
+    let length: f32 = track.points.windows(2).map(|w| w[0].distance(w[1])).sum();
+    (0..meters.len().saturating_sub(1))
+        .map(|i| {
+            let along = (meters[i] + meters[i + 1]) / 2.;
+            // zones on closed tracks may run across the start line
+            let inside = |zone: &BarrierZone| match track.closed {
+                true => (along - zone.from).rem_euclid(length) <= zone.to - zone.from,
+                false => (zone.from..=zone.to).contains(&along),
+            };
+            zones
+                .iter()
+                .rev()
+                .find(|zone| {
+                    let side = match zone.side {
+                        BarrierSide::Left => left,
+                        BarrierSide::Right => !left,
+                        BarrierSide::Both => true,
+                    };
+                    side && inside(zone)
+                })
+                .map(|zone| zone.kind)
+                .unwrap_or_default()
+        })
+        .collect()
+}
+
+/// Distance of every track point from the start line.
+fn track_meters(track: &Track, start: Vec3) -> Vec<f32> {
+    let mut distances: Vec<f32> = vec![0.];
+    for w in track.points.windows(2) {
+        distances.push(distances.last().unwrap() + w[0].distance(w[1]));
+    }
+    let start_distance = track
+        .points
+        .windows(2)
+        .enumerate()
+        .map(|(i, w)| {
+            let ab = w[1] - w[0];
+            let t = (start - w[0]).dot(ab) / ab.length_squared().max(f32::EPSILON);
+            let t = t.clamp(0., 1.);
+            (w[0] + ab * t, distances[i] + ab.length() * t)
+        })
+        .min_by(|(a, _), (b, _)| a.distance(start).total_cmp(&b.distance(start)))
+        .map(|(_, distance)| distance)
+        .unwrap_or(0.);
+    distances.iter().map(|d| d - start_distance).collect()
+}
+
+/// Wall along the points, as thick as its kind from the points outwards.
 pub fn spawn_walls(
     cmd: &mut Commands,
     meshes: &mut ResMut<Assets<Mesh>>,
     handled_materials: &Res<MaterialHandle>,
-    indices_input: &Vec<u32>,
-    points: &Vec<Vec3>,
-    normals_input: &Vec<Vec3>,
+    indices_input: &[u32],
+    points: &[Vec3],
+    normals_input: &[Vec3],
+    kind: BarrierKind,
 ) {
     let points_len = points.len() as u32;
     let material_lengh = 20.;
-    let width: f32 = 0.1;
-    let height: f32 = 0.6;
+    let (width, height) = kind.size();
     let heightv: Vec3 = Vec3::Y * height;
     let hw = width / 2.;
+    // the inner face stays on the wall line, thick barriers grow away from the road
+    let points: Vec<Vec3> = points
+        .iter()
+        .zip(normals_input)
+        .map(|(p, normal)| *p + *normal * hw)
+        .collect();
 
     let mut vertices: Vec<[f32; 3]> = vec![];
     let mut normals: Vec<[f32; 3]> = Vec::new();
@@ -80,9 +231,19 @@ pub fn spawn_walls(
     }
 
     let mut indices: Vec<u32> = vec![];
-    indices.extend(indices_input.clone());
+    indices.extend_from_slice(indices_input);
     indices.extend(indices_input.iter().map(|ind| ind + points_len * 2));
     indices.extend(indices_input.iter().map(|ind| ind + points_len * 4));
+    // the index pattern winds one way along the points, turn it around for walls facing the other
+    if let [a, b, c, ..] = indices_input[..] {
+        let v = |i: u32| Vec3::from(vertices[i as usize]);
+        let facing = (v(b) - v(a)).cross(v(c) - v(a));
+        if facing.dot(normals_input[a as usize / 2]) < 0. {
+            for triangle in indices.chunks_mut(3) {
+                triangle.swap(1, 2);
+            }
+        }
+    }
 
     let collider_vertices: Vec<Point3<Real>> = vertices
         .iter()
@@ -105,16 +266,96 @@ pub fn spawn_walls(
 
     cmd.spawn((
         Mesh3d(meshes.add(mesh)),
-        MeshMaterial3d(handled_materials.wall.clone()),
+        MeshMaterial3d(kind.material(handled_materials)),
         Transform::from_xyz(0., 0., 0.),
         Friction {
             combine_rule: CoefficientCombineRule::Min,
-            coefficient: 0.1,
+            coefficient: kind.friction(),
             ..default()
         },
         Collider::from(ColliderShape::trimesh(collider_vertices, collider_indices).unwrap()),
         ColliderScale::Absolute(Vec3::ONE),
         CollisionGroups::new(STATIC_GROUP, Group::ALL),
-        Restitution::coefficient(0.),
+        Restitution::coefficient(kind.restitution()),
+        Surface::Concrete,
+        TrackEntity,
     ));
 }
+
+/// Loose blocks or tire stacks along the wall segments, pushed outwards off the wall line.
+pub fn spawn_soft_barrier(
+    cmd: &mut Commands,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    handled_materials: &Res<MaterialHandle>,
+    indices_input: &[u32],
+    points: &[Vec3],
+    outward: &[Vec3],
+    kind: BarrierKind,
+) {
+    let (width, height) = kind.size();
+    let (length, mass, mesh, collider) = match kind {
+        BarrierKind::TireWall => (
+            width,
+            60.,
+            meshes.add(Cylinder::new(width / 2., height)),
+            Collider::cylinder(height / 2., width / 2.),
+        ),
+        _ => (
+            1.5,
+            120.,
+            meshes.add(Cuboid::new(width, height, 1.5)),
+            Collider::cuboid(width / 2., height / 2., 0.75),
+        ),
+    };
+    let material = kind.material(handled_materials);
+    for chunk in indices_input.chunks(6) {
+        let i = chunk[0] as usize / 2;
+        let (a, b) = (points[i], points[i + 1]);
+        let direction = (b - a).normalize_or_zero();
+        let count = (a.distance(b) / length).round().max(1.) as usize;
+        for k in 0..count {
+            let t = (k as f32 + 0.5) / count as f32;
+            let out = outward[i].lerp(outward[i + 1], t).normalize_or_zero();
+            let translation = a.lerp(b, t) + out * width / 2. + Vec3::Y * (height / 2. + 0.05);
+            cmd.spawn((
+                Mesh3d(mesh.clone()),
+                MeshMaterial3d(material.clone()),
+                Transform::from_translation(translation).looking_to(direction, Vec3::Y),
+                RigidBody::Dynamic,
+                collider.clone(),
+                ColliderMassProperties::Mass(mass),
+                Damping {
+                    linear_damping: 3.,
+                    angular_damping: 3.,
+                },
+                Friction::coefficient(kind.friction()),
+                Restitution::coefficient(kind.restitution()),
+                TrackEntity,
+            ));
+        }
+    }
+}
+
+/// Wall indices without the segments that run across another road.
+pub fn wall_indices_clear_of(indices: &[u32], points: &[Vec3], roads: &[&Track]) -> Vec<u32> {
+    indices
+        .chunks(6)
+        .filter(|chunk| {
+            // every segment quad starts with the left vertex of its first point
+            let i = chunk[0] as usize / 2;
+            !roads.iter().any(|road| {
+                road.road_contains(points[i], 0.5) || road.road_contains(points[i + 1], 0.5)
+            })
+        })
+        .flat_map(|chunk| chunk.iter().copied())
+        .collect()
+}
+
+/// Wall indices of the segments built as the given barrier.
+pub fn wall_indices_of(indices: &[u32], kinds: &[BarrierKind], kind: BarrierKind) -> Vec<u32> {
+    indices
+        .chunks(6)
+        .filter(|chunk| kinds.get(chunk[0] as usize / 2) == Some(&kind))
+        .flat_map(|chunk| chunk.iter().copied())
+        .collect()
+}
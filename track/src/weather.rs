@@ -0,0 +1,98 @@
+use crate::{ExtendedMaterialAsphalt, MaterialHandle};
+use bevy::prelude::*;
+use bevy_garage_car::Air;
+
+/// Wetness gained per second in full rain.
+const WEATHER_WETTING: f32 = 1. / 60.;
+/// Wetness lost per second at 20 °C without rain.
+const WEATHER_DRYING: f32 = 1. / 600.;
+/// Cars driving the racing line push the water away this many times faster.
+const WEATHER_LINE_DRYING: f32 = 3.;
+/// Half width of the dry line around the racing line.
+const WEATHER_LINE_HALF_WIDTH: f32 = 2.;
+const FOG_CLEAR_VISIBILITY: f32 = 5000.;
+const FOG_RAIN_VISIBILITY: f32 = 300.;
+
+#[derive(Resource, Debug)]
+pub struct Weather {
+    /// Rain intensity from 0 dry to 1 heavy rain.
+    pub rain: f32,
+    /// Water on the track from 0 dry to 1 soaked.
+    pub wetness: f32,
+    /// Water on the racing line, it dries faster than the rest of the track.
+    pub line_wetness: f32,
+    /// Ambient temperature in °C.
+    pub temperature: f32,
+}
+
+impl Default for Weather {
+    fn default() -> Self {
+        Self {
+            rain: 0.,
+            wetness: 0.,
+            line_wetness: 0.,
+            temperature: 20.,
+        }
+    }
+}
+
+impl Weather {
+    /// Wetness at a distance from the racing line.
+    pub fn wetness_at(&self, from_line: Option<f32>) -> f32 {
+        match from_line {
+            Some(from_line) if from_line < WEATHER_LINE_HALF_WIDTH => self.line_wetness,
+            _ => self.wetness,
+        }
+    }
+    /// Tyre grip multiplier, cold tyres grip less.
+    pub fn grip(&self) -> f32 {
+        1. - 0.01 * (15. - self.temperature).clamp(0., 15.)
+    }
+    /// Dry air density at the ambient temperature in kg/m3.
+    pub fn air_density(&self) -> f32 {
+        101325. / (287.05 * (self.temperature + 273.15))
+    }
+}
+
+pub fn weather_system(
+    time: Res<Time>,
+    mut weather: ResMut<Weather>,
+    air: Option<ResMut<Air>>,
+    mut fogs: Query<&mut DistanceFog>,
+    handled_materials: Res<MaterialHandle>,
+    mut asphalt_materials: ResMut<Assets<ExtendedMaterialAsphalt>>,
+    mut applied: Local<Option<(f32, f32)>>,
+) {
+    let dt = time.delta_secs();
+    let wetting = weather.rain * WEATHER_WETTING;
+    let drying = WEATHER_DRYING * (weather.temperature / 20.).max(0.2);
+    weather.wetness = (weather.wetness + (wetting - drying) * dt).clamp(0., 1.);
+    weather.line_wetness = (weather.line_wetness + (wetting - drying * WEATHER_LINE_DRYING) * dt)
+        .clamp(0., weather.wetness);
+
+    // the cars own the air, the weather only changes it
+    if let Some(mut air) = air {
+        air.density = weather.air_density();
+        air.drag = 1. + 0.1 * weather.rain;
+    }
+
+    let (rain, wetness) = (weather.rain, weather.wetness);
+    if applied.is_some_and(|(r, w)| r == rain && (w - wetness).abs() < 0.01) {
+        return;
+    }
+    *applied = Some((rain, wetness));
+
+    let visibility = FOG_CLEAR_VISIBILITY + (FOG_RAIN_VISIBILITY - FOG_CLEAR_VISIBILITY) * rain;
+    for mut fog in fogs.iter_mut() {
+        fog.falloff = FogFalloff::from_visibility_colors(
+            visibility,
+            Color::srgb(0.35, 0.5, 0.66),
+            Color::srgb(0.8, 0.844, 1.0),
+        );
+    }
+    for handle in [&handled_materials.asphalt, &handled_materials.asphalt_far] {
+        if let Some(asphalt) = asphalt_materials.get_mut(handle) {
+            asphalt.extension.wetness = wetness;
+        }
+    }
+}